@@ -0,0 +1,51 @@
+//! Batch cosine throughput on 768-dim embeddings.
+//!
+//! Compare the scalar and SIMD kernels with
+//!     cargo bench --bench cosine
+//!     cargo bench --bench cosine --features simd
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use engram_accel::bench::cosine_scores;
+
+const DIM: usize = 768;
+const ROWS: usize = 20_000;
+const PASSES: u32 = 50;
+
+/// Deterministic pseudo-random components in [-1, 1) (xorshift64).
+fn vectors(n: usize, mut seed: u64) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|_| {
+            (0..DIM)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    (seed >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn main() {
+    let query = vectors(1, 42).remove(0);
+    let store = vectors(ROWS, 7);
+
+    // Warm up rayon's pool and the caches before timing.
+    black_box(cosine_scores(&query, &store));
+
+    let start = Instant::now();
+    for _ in 0..PASSES {
+        black_box(cosine_scores(black_box(&query), black_box(&store)));
+    }
+    let per_pass = start.elapsed() / PASSES;
+
+    let kernel = if cfg!(feature = "simd") {
+        "simd"
+    } else {
+        "scalar"
+    };
+    println!("cosine {DIM}d x {ROWS} rows ({kernel}): {per_pass:?} per pass");
+}
@@ -1,30 +1,275 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
+use crate::vector::map_range;
+
+/// (s_fast, s_mid, s_slow) strengths of one memory.
+type Trace = (f64, f64, f64);
+
 /// Calculate decayed strength for a single memory.
 ///
 /// Formula: strength * exp(-rate * elapsed_days / (1 + factor * ln(1 + access_count)))
 /// Result is clamped to [0.0, 1.0].
+///
+/// `time_unit` (`"days"` by default, `"hours"` or `"seconds"`) gives the unit of
+/// `elapsed_days`, which is converted to days before decaying.
 #[pyfunction]
+#[pyo3(signature = (strength, elapsed_days, decay_rate, access_count, dampening_factor, time_unit="days"))]
 pub fn calculate_decayed_strength(
     strength: f64,
     elapsed_days: f64,
     decay_rate: f64,
     access_count: u32,
     dampening_factor: f64,
+    time_unit: &str,
+) -> PyResult<f64> {
+    let days = TimeUnit::parse(time_unit)?.to_days(elapsed_days);
+    Ok(decayed_strength(
+        strength,
+        days,
+        decay_rate,
+        access_count,
+        dampening_factor,
+    ))
+}
+
+/// `calculate_decayed_strength` with elapsed time taken from Unix timestamps
+/// (seconds): elapsed days = (now_unix - last_access_unix) / 86400. A last
+/// access after `now_unix` (clock skew) counts as no time elapsed.
+#[pyfunction]
+pub fn calculate_decayed_strength_at(
+    strength: f64,
+    last_access_unix: i64,
+    now_unix: i64,
+    decay_rate: f64,
+    access_count: u32,
+    dampening_factor: f64,
+) -> f64 {
+    decayed_strength(
+        strength,
+        elapsed_days_since(last_access_unix, now_unix),
+        decay_rate,
+        access_count,
+        dampening_factor,
+    )
+}
+
+/// Batch version of `calculate_decayed_strength` for flat single-strength memories.
+///
+/// Missing elapsed days or access counts (shorter inputs) default to 0.
+/// Large batches are decayed in parallel with rayon; output order matches input.
+#[pyfunction]
+pub fn calculate_decayed_strength_batch(
+    strengths: Vec<f64>,
+    elapsed_days: Vec<f64>,
+    access_counts: Vec<u32>,
+    decay_rate: f64,
+    dampening_factor: f64,
+) -> Vec<f64> {
+    map_range(strengths.len(), |i| {
+        decayed_strength(
+            strengths[i],
+            elapsed_days.get(i).copied().unwrap_or(0.0),
+            decay_rate,
+            access_counts.get(i).copied().unwrap_or(0),
+            dampening_factor,
+        )
+    })
+}
+
+/// Query-time relevance: each similarity times its memory's decayed strength.
+///
+/// Equivalent to `calculate_decayed_strength_batch` followed by an element-wise
+/// multiply, done in one pass. All four input lists must have the same length
+/// or a ValueError is raised.
+#[pyfunction]
+pub fn decayed_relevance(
+    similarities: Vec<f64>,
+    strengths: Vec<f64>,
+    elapsed_days: Vec<f64>,
+    access_counts: Vec<u32>,
+    decay_rate: f64,
+    dampening_factor: f64,
+) -> PyResult<Vec<f64>> {
+    let n = similarities.len();
+    for (name, len) in [
+        ("strengths", strengths.len()),
+        ("elapsed_days", elapsed_days.len()),
+        ("access_counts", access_counts.len()),
+    ] {
+        if len != n {
+            return Err(PyValueError::new_err(format!(
+                "{name} has length {len} but similarities has length {n}"
+            )));
+        }
+    }
+
+    Ok(map_range(n, |i| {
+        similarities[i]
+            * decayed_strength(
+                strengths[i],
+                elapsed_days[i],
+                decay_rate,
+                access_counts[i],
+                dampening_factor,
+            )
+    }))
+}
+
+/// Power-law counterpart of `calculate_decayed_strength`.
+///
+/// Formula: strength * (1 + elapsed_days)^(-rate / (1 + factor * ln(1 + access_count)))
+/// Result is clamped to [0.0, 1.0].
+#[pyfunction]
+pub fn calculate_decayed_strength_powerlaw(
+    strength: f64,
+    elapsed_days: f64,
+    decay_rate: f64,
+    access_count: u32,
+    dampening_factor: f64,
 ) -> f64 {
     if strength.is_nan() {
         return 0.0;
     }
-    let dampening = 1.0 + dampening_factor * (1.0 + access_count as f64).ln();
-    let decayed = strength * (-decay_rate * elapsed_days / dampening).exp();
+    let dampening = dampening(access_count, dampening_factor);
+    let decayed = strength * (1.0 + elapsed_days).powf(-decay_rate / dampening);
     decayed.clamp(0.0, 1.0)
 }
 
+/// Linear decay that bottoms out at a floor instead of an exponential tail.
+///
+/// Formula: max(floor, strength - rate * elapsed_days), with `floor` clamped
+/// into [0.0, 1.0] (NaN counts as 0.0) and the result capped at 1.0. NaN
+/// strength decays to the floor.
+#[pyfunction]
+pub fn calculate_decayed_strength_linear(
+    strength: f64,
+    elapsed_days: f64,
+    decay_rate: f64,
+    floor: f64,
+) -> f64 {
+    let floor = if floor.is_nan() {
+        0.0
+    } else {
+        floor.clamp(0.0, 1.0)
+    };
+    if strength.is_nan() {
+        return floor;
+    }
+    (strength - decay_rate * elapsed_days).clamp(floor, 1.0)
+}
+
+/// Decay a memory, then credit its accesses back ("testing effect").
+///
+/// Formula: decayed + gain * (1 - decayed) * ln(1 + access_count), where
+/// `decayed` is `calculate_decayed_strength`. Result is clamped to [0.0, 1.0].
+#[pyfunction]
+pub fn reinforce_and_decay(
+    strength: f64,
+    elapsed_days: f64,
+    decay_rate: f64,
+    access_count: u32,
+    reinforcement_gain: f64,
+    dampening_factor: f64,
+) -> f64 {
+    let decayed = decayed_strength(
+        strength,
+        elapsed_days,
+        decay_rate,
+        access_count,
+        dampening_factor,
+    );
+    let boost = reinforcement_gain * (1.0 - decayed) * (1.0 + access_count as f64).ln();
+    (decayed + boost).clamp(0.0, 1.0)
+}
+
+/// Days until `calculate_decayed_strength` drops to `threshold`.
+///
+/// Formula: dampening / rate * ln(strength / threshold)
+/// Returns 0.0 when strength is already at or below the threshold, and
+/// infinity when it never gets there (non-positive rate or threshold).
+#[pyfunction]
+pub fn days_until_threshold(
+    strength: f64,
+    threshold: f64,
+    decay_rate: f64,
+    access_count: u32,
+    dampening_factor: f64,
+) -> f64 {
+    if strength.is_nan() || strength <= threshold {
+        return 0.0;
+    }
+    if decay_rate <= 0.0 || threshold <= 0.0 {
+        return f64::INFINITY;
+    }
+    let dampening = dampening(access_count, dampening_factor);
+    dampening / decay_rate * (strength / threshold).ln()
+}
+
+/// Days for `calculate_decayed_strength` to halve a memory's strength.
+///
+/// Formula: ln(2) * (1 + factor * ln(1 + access_count)) / rate
+/// Returns infinity for a non-positive rate, which never halves anything.
+#[pyfunction]
+pub fn half_life_days(decay_rate: f64, access_count: u32, dampening_factor: f64) -> f64 {
+    if decay_rate <= 0.0 {
+        return f64::INFINITY;
+    }
+    std::f64::consts::LN_2 * dampening(access_count, dampening_factor) / decay_rate
+}
+
+/// Retention curve of one memory: `calculate_decayed_strength` at each of `days`.
+///
+/// Offsets are evaluated independently, so they need not be sorted or evenly spaced.
+#[pyfunction]
+pub fn simulate_decay(
+    initial_strength: f64,
+    decay_rate: f64,
+    access_count: u32,
+    dampening_factor: f64,
+    days: Vec<f64>,
+) -> Vec<f64> {
+    days.iter()
+        .map(|&d| {
+            decayed_strength(
+                initial_strength,
+                d,
+                decay_rate,
+                access_count,
+                dampening_factor,
+            )
+        })
+        .collect()
+}
+
+/// Effective decay rate that slows with use and speeds up with neglect.
+///
+/// Formula: base_rate * (1 + s * ln(1 + recency_days)) / (1 + s * ln(1 + access_count))
+/// where `s` is `sensitivity`. With `s == 0` this is just `base_rate`; both
+/// adjustments grow logarithmically, so no finite count drives the rate to zero.
+/// Negative `recency_days` (clock skew) counts as 0. Feed the result to the
+/// decay functions as `decay_rate`.
+#[pyfunction]
+pub fn adaptive_decay_rate(
+    base_rate: f64,
+    access_count: u32,
+    recency_days: f64,
+    sensitivity: f64,
+) -> f64 {
+    let neglect = 1.0 + sensitivity * (1.0 + recency_days.max(0.0)).ln();
+    base_rate * neglect / dampening(access_count, sensitivity)
+}
+
 /// Batch decay for multi-trace strength values.
 ///
 /// Each trace is (s_fast, s_mid, s_slow). Returns decayed traces.
-/// Uses per-trace decay rates and shared dampening formula.
+/// Uses per-trace decay rates and the same dampening formula as
+/// `calculate_decayed_strength` (`dampening_factor` defaults to 0.5).
+/// Large batches are decayed in parallel with rayon; output order matches input.
+/// `time_unit` works as in `calculate_decayed_strength`.
 #[pyfunction]
+#[pyo3(signature = (traces, elapsed_days, access_counts, fast_rate, mid_rate, slow_rate, dampening_factor=0.5, time_unit="days"))]
+#[allow(clippy::too_many_arguments)]
 pub fn decay_traces_batch(
     traces: Vec<(f64, f64, f64)>,
     elapsed_days: Vec<f64>,
@@ -32,31 +277,362 @@ pub fn decay_traces_batch(
     fast_rate: f64,
     mid_rate: f64,
     slow_rate: f64,
+    dampening_factor: f64,
+    time_unit: &str,
+) -> PyResult<Vec<(f64, f64, f64)>> {
+    let unit = TimeUnit::parse(time_unit)?;
+    Ok(map_range(traces.len(), |i| {
+        decay_trace(
+            traces[i],
+            unit.to_days(elapsed_days.get(i).copied().unwrap_or(0.0)),
+            access_counts.get(i).copied().unwrap_or(0),
+            (fast_rate, mid_rate, slow_rate),
+            dampening_factor,
+        )
+    }))
+}
+
+/// `decay_traces_batch` with elapsed time taken from each trace's last-access
+/// Unix timestamp against a shared `now_unix`, as in
+/// `calculate_decayed_strength_at`. Traces without a timestamp (shorter input)
+/// are treated as accessed at `now_unix`.
+#[pyfunction]
+#[pyo3(signature = (traces, last_access_unix, now_unix, access_counts, fast_rate, mid_rate, slow_rate, dampening_factor=0.5))]
+#[allow(clippy::too_many_arguments)]
+pub fn decay_traces_batch_at(
+    traces: Vec<(f64, f64, f64)>,
+    last_access_unix: Vec<i64>,
+    now_unix: i64,
+    access_counts: Vec<u32>,
+    fast_rate: f64,
+    mid_rate: f64,
+    slow_rate: f64,
+    dampening_factor: f64,
 ) -> Vec<(f64, f64, f64)> {
-    let n = traces.len();
-    let mut results = Vec::with_capacity(n);
-
-    for i in 0..n {
-        let (s_fast, s_mid, s_slow) = traces[i];
-        let days = if i < elapsed_days.len() {
-            elapsed_days[i]
-        } else {
-            0.0
-        };
-        let access = if i < access_counts.len() {
-            access_counts[i]
-        } else {
-            0
+    map_range(traces.len(), |i| {
+        decay_trace(
+            traces[i],
+            last_access_unix
+                .get(i)
+                .map_or(0.0, |&t| elapsed_days_since(t, now_unix)),
+            access_counts.get(i).copied().unwrap_or(0),
+            (fast_rate, mid_rate, slow_rate),
+            dampening_factor,
+        )
+    })
+}
+
+/// `decay_traces_batch` with its own (fast, mid, slow) rates for every trace.
+///
+/// A rate vector shorter than `traces` falls back to its first rate for the
+/// remaining traces (0.0 if it is empty).
+#[pyfunction]
+#[pyo3(signature = (traces, elapsed_days, access_counts, fast_rates, mid_rates, slow_rates, dampening_factor=0.5))]
+pub fn decay_traces_batch_per_item(
+    traces: Vec<(f64, f64, f64)>,
+    elapsed_days: Vec<f64>,
+    access_counts: Vec<u32>,
+    fast_rates: Vec<f64>,
+    mid_rates: Vec<f64>,
+    slow_rates: Vec<f64>,
+    dampening_factor: f64,
+) -> Vec<(f64, f64, f64)> {
+    let rate_at = |rates: &[f64], i: usize| rates.get(i).or(rates.first()).copied().unwrap_or(0.0);
+    map_range(traces.len(), |i| {
+        decay_trace(
+            traces[i],
+            elapsed_days.get(i).copied().unwrap_or(0.0),
+            access_counts.get(i).copied().unwrap_or(0),
+            (
+                rate_at(&fast_rates, i),
+                rate_at(&mid_rates, i),
+                rate_at(&slow_rates, i),
+            ),
+            dampening_factor,
+        )
+    })
+}
+
+/// `decay_traces_batch` plus the indices of traces that are now weak.
+///
+/// A trace is weak when the strongest of its three decayed strengths is below
+/// `prune_threshold`. Indices are returned in ascending order.
+#[pyfunction]
+#[pyo3(signature = (traces, elapsed_days, access_counts, fast_rate, mid_rate, slow_rate, prune_threshold, dampening_factor=0.5))]
+#[allow(clippy::too_many_arguments)]
+pub fn decay_and_flag_weak(
+    traces: Vec<(f64, f64, f64)>,
+    elapsed_days: Vec<f64>,
+    access_counts: Vec<u32>,
+    fast_rate: f64,
+    mid_rate: f64,
+    slow_rate: f64,
+    prune_threshold: f64,
+    dampening_factor: f64,
+) -> (Vec<(f64, f64, f64)>, Vec<usize>) {
+    let decayed = map_range(traces.len(), |i| {
+        decay_trace(
+            traces[i],
+            elapsed_days.get(i).copied().unwrap_or(0.0),
+            access_counts.get(i).copied().unwrap_or(0),
+            (fast_rate, mid_rate, slow_rate),
+            dampening_factor,
+        )
+    });
+    let weak = decayed
+        .iter()
+        .enumerate()
+        .filter(|(_, &(s_fast, s_mid, s_slow))| s_fast.max(s_mid).max(s_slow) < prune_threshold)
+        .map(|(i, _)| i)
+        .collect();
+    (decayed, weak)
+}
+
+/// Collapse (s_fast, s_mid, s_slow) traces into one strength per trace.
+///
+/// Each result is the weighted sum of the three traces divided by the weight
+/// total. A zero weight total yields 0.0 for every trace.
+#[pyfunction]
+pub fn consolidate_traces(
+    traces: Vec<(f64, f64, f64)>,
+    w_fast: f64,
+    w_mid: f64,
+    w_slow: f64,
+) -> Vec<f64> {
+    let total = w_fast + w_mid + w_slow;
+    if total == 0.0 {
+        return vec![0.0; traces.len()];
+    }
+    traces
+        .iter()
+        .map(|&(s_fast, s_mid, s_slow)| (w_fast * s_fast + w_mid * s_mid + w_slow * s_slow) / total)
+        .collect()
+}
+
+/// Merge the traces of two duplicate memories: the element-wise max of their
+/// (s_fast, s_mid, s_slow) strengths and the sum of their access counts
+/// (saturating at `u32::MAX`).
+#[pyfunction]
+pub fn merge_traces(a: Trace, b: Trace, access_a: u32, access_b: u32) -> (Trace, u32) {
+    (
+        (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        access_a.saturating_add(access_b),
+    )
+}
+
+/// `merge_traces` over a list of `(a, b, access_a, access_b)` tuples.
+#[pyfunction]
+pub fn merge_traces_batch(pairs: Vec<(Trace, Trace, u32, u32)>) -> Vec<(Trace, u32)> {
+    pairs
+        .into_iter()
+        .map(|(a, b, access_a, access_b)| merge_traces(a, b, access_a, access_b))
+        .collect()
+}
+
+/// Exponential moving-average blend of new observations into current strengths.
+///
+/// Formula: alpha * observation + (1 - alpha) * current, with `alpha` clamped
+/// into [0.0, 1.0]. Mismatched lengths raise a ValueError.
+#[pyfunction]
+pub fn ema_update(current: Vec<f64>, observations: Vec<f64>, alpha: f64) -> PyResult<Vec<f64>> {
+    if current.len() != observations.len() {
+        return Err(PyValueError::new_err(format!(
+            "observations has length {} but current has length {}",
+            observations.len(),
+            current.len()
+        )));
+    }
+    let alpha = alpha.clamp(0.0, 1.0);
+    Ok(current
+        .iter()
+        .zip(observations.iter())
+        .map(|(c, o)| alpha * o + (1.0 - alpha) * c)
+        .collect())
+}
+
+/// Largest `DecayTable` grid (128 MiB of f64s); bigger grids are rejected
+/// rather than allocated.
+const MAX_DECAY_TABLE_ENTRIES: usize = 1 << 24;
+
+/// Precomputed decay factors `exp(-rate * days / dampening)` on a grid of
+/// elapsed-days and access-count buckets, trading a small quantization error
+/// for skipping `exp` on every lookup.
+///
+/// Elapsed days are rounded to the nearest multiple of `day_step` (the grid
+/// resolution) up to `max_days`; later times fall back to computing `exp`
+/// exactly. Access counts above `max_access_count` use the last bucket. The
+/// table holds `(max_days / day_step + 1) * (max_access_count + 1)` f64s,
+/// at most `MAX_DECAY_TABLE_ENTRIES`.
+#[pyclass]
+pub struct DecayTable {
+    #[pyo3(get)]
+    decay_rate: f64,
+    #[pyo3(get)]
+    dampening_factor: f64,
+    #[pyo3(get)]
+    day_step: f64,
+    #[pyo3(get)]
+    max_access_count: u32,
+    day_buckets: usize,
+    /// Row-major by access count: `factors[access * day_buckets + day]`.
+    factors: Vec<f64>,
+}
+
+#[pymethods]
+impl DecayTable {
+    /// Raises ValueError unless `day_step` is positive and finite, `max_days`
+    /// is non-negative and finite, and the grid fits in
+    /// `MAX_DECAY_TABLE_ENTRIES`.
+    #[new]
+    #[pyo3(signature = (decay_rate, dampening_factor, max_days, day_step=0.1, max_access_count=255))]
+    fn new(
+        decay_rate: f64,
+        dampening_factor: f64,
+        max_days: f64,
+        day_step: f64,
+        max_access_count: u32,
+    ) -> PyResult<Self> {
+        if !day_step.is_finite() || day_step <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "day_step must be positive and finite, got {day_step}"
+            )));
+        }
+        if !max_days.is_finite() || max_days < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "max_days must be non-negative and finite, got {max_days}"
+            )));
+        }
+
+        let too_large = || {
+            PyValueError::new_err(format!(
+                "a DecayTable with max_days={max_days}, day_step={day_step} and \
+                 max_access_count={max_access_count} exceeds {MAX_DECAY_TABLE_ENTRIES} entries"
+            ))
         };
+        let steps = (max_days / day_step).ceil();
+        if steps >= MAX_DECAY_TABLE_ENTRIES as f64 {
+            return Err(too_large());
+        }
+        let day_buckets = (steps as usize).checked_add(1).ok_or_else(too_large)?;
+        let access_buckets = (max_access_count as usize)
+            .checked_add(1)
+            .ok_or_else(too_large)?;
+        let len = access_buckets
+            .checked_mul(day_buckets)
+            .filter(|&len| len <= MAX_DECAY_TABLE_ENTRIES)
+            .ok_or_else(too_large)?;
+        let factors = map_range(len, |i| {
+            let dampening = dampening((i / day_buckets) as u32, dampening_factor);
+            let days = (i % day_buckets) as f64 * day_step;
+            (-decay_rate * days / dampening).exp()
+        });
+
+        Ok(Self {
+            decay_rate,
+            dampening_factor,
+            day_step,
+            max_access_count,
+            day_buckets,
+            factors,
+        })
+    }
+
+    /// Decay factor for each (elapsed_days[i], access_counts[i]) pair; multiply
+    /// a strength by it to decay the strength. Negative or NaN elapsed days count
+    /// as 0. Raises ValueError if the two lists differ in length.
+    fn lookup_batch(&self, elapsed_days: Vec<f64>, access_counts: Vec<u32>) -> PyResult<Vec<f64>> {
+        if elapsed_days.len() != access_counts.len() {
+            return Err(PyValueError::new_err(format!(
+                "access_counts has length {} but elapsed_days has length {}",
+                access_counts.len(),
+                elapsed_days.len()
+            )));
+        }
 
-        let dampening = 1.0 + 0.5 * (1.0 + access as f64).ln();
+        Ok(map_range(elapsed_days.len(), |i| {
+            let access = access_counts[i].min(self.max_access_count);
+            let days = elapsed_days[i].max(0.0);
+            let day = (days / self.day_step).round();
+            if day < self.day_buckets as f64 {
+                self.factors[access as usize * self.day_buckets + day as usize]
+            } else {
+                let dampening = dampening(access, self.dampening_factor);
+                (-self.decay_rate * days / dampening).exp()
+            }
+        }))
+    }
+}
+
+/// Unit of an elapsed-time argument; decay rates are always per day.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TimeUnit {
+    Days,
+    Hours,
+    Seconds,
+}
+
+impl TimeUnit {
+    fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "days" => Ok(TimeUnit::Days),
+            "hours" => Ok(TimeUnit::Hours),
+            "seconds" => Ok(TimeUnit::Seconds),
+            other => Err(PyValueError::new_err(format!(
+                "unknown time_unit '{other}', expected 'days', 'hours' or 'seconds'"
+            ))),
+        }
+    }
 
-        let new_fast = (s_fast * (-fast_rate * days / dampening).exp()).clamp(0.0, 1.0);
-        let new_mid = (s_mid * (-mid_rate * days / dampening).exp()).clamp(0.0, 1.0);
-        let new_slow = (s_slow * (-slow_rate * days / dampening).exp()).clamp(0.0, 1.0);
+    fn to_days(self, elapsed: f64) -> f64 {
+        match self {
+            TimeUnit::Days => elapsed,
+            TimeUnit::Hours => elapsed / 24.0,
+            TimeUnit::Seconds => elapsed / 86_400.0,
+        }
+    }
+}
 
-        results.push((new_fast, new_mid, new_slow));
+/// Shared body of `calculate_decayed_strength` once elapsed time is in days.
+fn decayed_strength(
+    strength: f64,
+    elapsed_days: f64,
+    decay_rate: f64,
+    access_count: u32,
+    dampening_factor: f64,
+) -> f64 {
+    if strength.is_nan() {
+        return 0.0;
     }
+    let dampening = dampening(access_count, dampening_factor);
+    let decayed = strength * (-decay_rate * elapsed_days / dampening).exp();
+    decayed.clamp(0.0, 1.0)
+}
+
+/// Days from `last_access_unix` to `now_unix`, clamped at 0.0 for clock skew.
+fn elapsed_days_since(last_access_unix: i64, now_unix: i64) -> f64 {
+    let seconds = now_unix.saturating_sub(last_access_unix).max(0);
+    TimeUnit::Seconds.to_days(seconds as f64)
+}
+
+/// Decay one (s_fast, s_mid, s_slow) trace with its (fast, mid, slow) rates.
+fn decay_trace(
+    (s_fast, s_mid, s_slow): (f64, f64, f64),
+    elapsed_days: f64,
+    access_count: u32,
+    (fast_rate, mid_rate, slow_rate): (f64, f64, f64),
+    dampening_factor: f64,
+) -> (f64, f64, f64) {
+    let dampening = dampening(access_count, dampening_factor);
+
+    let new_fast = (s_fast * (-fast_rate * elapsed_days / dampening).exp()).clamp(0.0, 1.0);
+    let new_mid = (s_mid * (-mid_rate * elapsed_days / dampening).exp()).clamp(0.0, 1.0);
+    let new_slow = (s_slow * (-slow_rate * elapsed_days / dampening).exp()).clamp(0.0, 1.0);
+
+    (new_fast, new_mid, new_slow)
+}
 
-    results
+/// Access-count dampening shared by every decay formula: `1 + factor * ln(1 + access_count)`.
+#[inline]
+fn dampening(access_count: u32, dampening_factor: f64) -> f64 {
+    1.0 + dampening_factor * (1.0 + access_count as f64).ln()
 }
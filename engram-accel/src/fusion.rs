@@ -0,0 +1,204 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use crate::scoring::{bm25_scores, IdfVariant};
+use crate::topk::TopK;
+use crate::vector::cosine_scores;
+
+/// Reciprocal rank fusion of several score lists over the same candidates.
+///
+/// Each list is ranked best first (rank 1; ties keep index order, NaN ranks
+/// last) and every candidate receives `1 / (k + rank)` from each list.
+/// Raises ValueError if the lists differ in length.
+#[pyfunction]
+#[pyo3(signature = (score_lists, k=60.0))]
+pub fn reciprocal_rank_fusion(score_lists: Vec<Vec<f64>>, k: f64) -> PyResult<Vec<f64>> {
+    let n = check_same_len(&score_lists)?;
+    let mut fused = vec![0.0; n];
+
+    for scores in &score_lists {
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| rank_key(scores[b]).total_cmp(&rank_key(scores[a])));
+        for (rank, &i) in order.iter().enumerate() {
+            fused[i] += 1.0 / (k + (rank + 1) as f64);
+        }
+    }
+
+    Ok(fused)
+}
+
+/// Weighted sum of several score lists over the same candidates.
+/// Raises ValueError if the lists differ in length or `weights` does not
+/// have one entry per list.
+#[pyfunction]
+pub fn weighted_fusion(score_lists: Vec<Vec<f64>>, weights: Vec<f64>) -> PyResult<Vec<f64>> {
+    if weights.len() != score_lists.len() {
+        return Err(PyValueError::new_err(format!(
+            "got {} score lists but {} weights",
+            score_lists.len(),
+            weights.len()
+        )));
+    }
+    let n = check_same_len(&score_lists)?;
+    let mut fused = vec![0.0; n];
+
+    for (scores, &weight) in score_lists.iter().zip(&weights) {
+        for (acc, &s) in fused.iter_mut().zip(scores) {
+            *acc += weight * s;
+        }
+    }
+
+    Ok(fused)
+}
+
+/// Min-max scale scores into [0, 1]. All-equal scores map to 0.5.
+#[pyfunction]
+pub fn minmax_normalize(scores: Vec<f64>) -> Vec<f64> {
+    let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    if range == 0.0 {
+        return vec![0.5; scores.len()];
+    }
+    scores.iter().map(|s| (s - min) / range).collect()
+}
+
+/// Standardize scores to zero mean and unit (population) standard deviation.
+/// All-equal scores map to 0.0.
+#[pyfunction]
+pub fn zscore_normalize(scores: Vec<f64>) -> Vec<f64> {
+    if scores.is_empty() {
+        return scores;
+    }
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let std = (scores.iter().map(|s| (s - mean) * (s - mean)).sum::<f64>() / n).sqrt();
+    if std == 0.0 {
+        return vec![0.0; scores.len()];
+    }
+    scores.iter().map(|s| (s - mean) / std).collect()
+}
+
+/// Softmax of `scores` at `temperature`, turning raw similarities into
+/// weights that sum to 1. Lower temperatures sharpen the distribution.
+/// The max score is subtracted before exponentiating for numerical stability.
+/// Raises ValueError if `temperature` is not positive or any score is not
+/// finite, since an infinite max leaves no well-defined distribution.
+#[pyfunction]
+pub fn softmax(scores: Vec<f64>, temperature: f64) -> PyResult<Vec<f64>> {
+    if temperature <= 0.0 || temperature.is_nan() {
+        return Err(PyValueError::new_err(format!(
+            "temperature must be positive, got {temperature}"
+        )));
+    }
+    if let Some(i) = scores.iter().position(|s| !s.is_finite()) {
+        return Err(PyValueError::new_err(format!(
+            "scores must be finite, got {} at index {i}",
+            scores[i]
+        )));
+    }
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = scores
+        .iter()
+        .map(|s| ((s - max) / temperature).exp())
+        .collect();
+    let total: f64 = exps.iter().sum();
+    Ok(exps.into_iter().map(|e| e / total).collect())
+}
+
+/// Hybrid lexical + semantic relevance in one call.
+///
+/// BM25 scores of `documents` and cosine similarities of `doc_vecs` are each
+/// min-max normalized (as in `minmax_normalize`) and blended as
+/// `alpha * semantic + (1 - alpha) * lexical`. `doc_vecs[i]` must be the
+/// embedding of `documents[i]`; differing counts raise a ValueError.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub fn hybrid_score(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    query_vec: Vec<f64>,
+    doc_vecs: Vec<Vec<f64>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    alpha: f64,
+) -> PyResult<Vec<f64>> {
+    if doc_vecs.len() != documents.len() {
+        return Err(PyValueError::new_err(format!(
+            "got {} documents but {} doc_vecs",
+            documents.len(),
+            doc_vecs.len()
+        )));
+    }
+
+    let lexical = minmax_normalize(bm25_scores(
+        &query_terms,
+        &documents,
+        total_docs,
+        avg_doc_len,
+        k1,
+        b,
+        IdfVariant::Bm25,
+    ));
+    let semantic = minmax_normalize(cosine_scores(&query_vec, &doc_vecs));
+    Ok(lexical
+        .iter()
+        .zip(&semantic)
+        .map(|(lex, sem)| alpha * sem + (1.0 - alpha) * lex)
+        .collect())
+}
+
+/// Merge per-shard top-k results into a global top-k.
+///
+/// Each shard is a list of (id, score) pairs in any order. Pairs are
+/// collected into a hash map keyed by id, keeping each id's highest score,
+/// and the best `k` are then selected from it; shards are not k-way merged,
+/// so they need not be sorted. Returns at most `k` pairs sorted by
+/// descending score, ties broken by the smaller id. NaN scores rank last,
+/// as in `reciprocal_rank_fusion`, and are returned unchanged.
+#[pyfunction]
+pub fn merge_topk(shard_results: Vec<Vec<(u64, f64)>>, k: usize) -> Vec<(u64, f64)> {
+    let mut best: HashMap<u64, f64> = HashMap::new();
+    for &(id, score) in shard_results.iter().flatten() {
+        best.entry(id)
+            .and_modify(|s| *s = s.max(score))
+            .or_insert(score);
+    }
+
+    let mut top = TopK::new(k);
+    for (&id, &score) in &best {
+        top.push(id, rank_key(score));
+    }
+    top.into_sorted_vec()
+        .into_iter()
+        .map(|(id, _)| (id, best[&id]))
+        .collect()
+}
+
+/// Shared length of every score list, or a ValueError naming the first mismatch.
+fn check_same_len(score_lists: &[Vec<f64>]) -> PyResult<usize> {
+    let n = score_lists.first().map_or(0, Vec::len);
+    if let Some((i, scores)) = score_lists
+        .iter()
+        .enumerate()
+        .find(|(_, scores)| scores.len() != n)
+    {
+        return Err(PyValueError::new_err(format!(
+            "score list {i} has {} entries, expected {n}",
+            scores.len()
+        )));
+    }
+    Ok(n)
+}
+
+/// Sort key that ranks NaN below every real score.
+fn rank_key(score: f64) -> f64 {
+    if score.is_nan() {
+        f64::NEG_INFINITY
+    } else {
+        score
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use hnsw_rs::anndists::dist::DistCosine;
+use hnsw_rs::hnsw::Hnsw;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Layers in the HNSW graph; hnsw_rs caps this at 16.
+const MAX_LAYERS: usize = 16;
+/// Most links per node hnsw_rs accepts; above it the library exits the process.
+const MAX_LINKS: usize = 256;
+
+/// Approximate nearest-neighbour index over cosine distance (HNSW graph).
+///
+/// Vectors are buffered by `add` and indexed by `build`; vectors added or
+/// replaced after a build are only searchable once `build` runs again.
+#[pyclass]
+pub struct HnswIndex {
+    ids: Vec<u64>,
+    vectors: Vec<Vec<f32>>,
+    /// Position of each id in `ids`/`vectors`, which is also its graph data id.
+    slots: HashMap<u64, usize>,
+    graph: Option<Hnsw<'static, f32, DistCosine>>,
+}
+
+#[pymethods]
+impl HnswIndex {
+    #[new]
+    fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            slots: HashMap::new(),
+            graph: None,
+        }
+    }
+
+    /// Queue `vector` under `id` for the next `build`, replacing any vector
+    /// already added under that id.
+    /// Raises ValueError if its dimension differs from the vectors already added.
+    fn add(&mut self, id: u64, vector: Vec<f32>) -> PyResult<()> {
+        self.check_dim(&vector, "vector")?;
+        match self.slots.get(&id) {
+            Some(&slot) => self.vectors[slot] = vector,
+            None => {
+                self.slots.insert(id, self.ids.len());
+                self.ids.push(id);
+                self.vectors.push(vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// (Re)build the graph over every added vector. `m` is the maximum number
+    /// of links per node and `ef_construction` the candidate list size while
+    /// inserting; larger values raise recall at the cost of build time.
+    /// Raises ValueError unless `m` is in 2..=256 and `ef_construction` is
+    /// positive.
+    #[pyo3(signature = (ef_construction=200, m=16))]
+    fn build(&mut self, py: Python<'_>, ef_construction: usize, m: usize) -> PyResult<()> {
+        if !(2..=MAX_LINKS).contains(&m) {
+            return Err(PyValueError::new_err(format!(
+                "m must be in 2..={MAX_LINKS}, got {m}"
+            )));
+        }
+        if ef_construction == 0 {
+            return Err(PyValueError::new_err("ef_construction must be positive"));
+        }
+        let graph = py.allow_threads(|| {
+            let graph = Hnsw::new(
+                m,
+                self.vectors.len().max(1),
+                MAX_LAYERS,
+                ef_construction,
+                DistCosine {},
+            );
+            let data: Vec<(&Vec<f32>, usize)> = self.vectors.iter().zip(0..).collect();
+            graph.parallel_insert(&data);
+            graph
+        });
+        self.graph = Some(graph);
+        Ok(())
+    }
+
+    /// Approximate top-k cosine matches as (id, similarity), best first.
+    /// `ef` is the search candidate list size (at least `k`). As elsewhere in
+    /// the crate, a zero-norm query or stored vector scores 0.0.
+    /// Raises ValueError if `build` has not run or the query dimension is wrong.
+    #[pyo3(signature = (query, k, ef=64))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        query: Vec<f32>,
+        k: usize,
+        ef: usize,
+    ) -> PyResult<Vec<(u64, f64)>> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("index is not built, call build() first"))?;
+        self.check_dim(&query, "query")?;
+        if k == 0 || self.vectors.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let neighbours = py.allow_threads(|| graph.search(&query, k, ef.max(k)));
+        let query_is_zero = is_zero(&query);
+        let mut hits: Vec<(u64, f64)> = neighbours
+            .into_iter()
+            .map(|n| {
+                let similarity = if query_is_zero || is_zero(&self.vectors[n.d_id]) {
+                    0.0
+                } else {
+                    1.0 - n.distance as f64
+                };
+                (self.ids[n.d_id], similarity)
+            })
+            .collect();
+        // hnsw_rs puts zero vectors at distance 0; re-rank them after the remap.
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(hits)
+    }
+
+    fn __len__(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl HnswIndex {
+    fn check_dim(&self, vector: &[f32], what: &str) -> PyResult<()> {
+        match self.vectors.first() {
+            Some(first) if first.len() != vector.len() => Err(PyValueError::new_err(format!(
+                "{what} has dimension {}, index has dimension {}",
+                vector.len(),
+                first.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether every component is zero, leaving cosine undefined.
+fn is_zero(vector: &[f32]) -> bool {
+    vector.iter().all(|&x| x == 0.0)
+}
@@ -1,23 +1,149 @@
+// The #[pyfunction] expansion in pyo3 0.22 trips this lint on every PyResult return.
+#![allow(clippy::useless_conversion)]
+
 use pyo3::prelude::*;
 
 mod decay;
+mod fusion;
+#[cfg(feature = "hnsw")]
+mod hnsw;
+mod pq;
 mod scoring;
+mod topk;
 mod vector;
 
+/// Pure-Rust kernels exposed for `benches/`; not part of the Python API.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::vector::cosine_scores;
+}
+
 /// engram_accel — Rust acceleration for the Engram memory layer.
 #[pymodule]
 fn engram_accel(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Vector operations
     m.add_function(wrap_pyfunction!(vector::cosine_similarity, m)?)?;
     m.add_function(wrap_pyfunction!(vector::cosine_similarity_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_chunked, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        vector::cosine_similarity_batch_checked,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_subset, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::validate_dimensions, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_f32, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_batch_f32, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_batch_np, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::angular_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::weighted_cosine_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::masked_cosine_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::normalize_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::is_normalized_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::centroid, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::sparse_cosine_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::jaccard_similarity_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::hamming_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::quantize_vectors, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_batch_i8, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::pairwise_cosine_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::set_diversity, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_similarity_matrix, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_above_threshold, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::pearson_correlation_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::spearman_correlation_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::find_duplicates, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::euclidean_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::squared_euclidean_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::manhattan_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::minkowski_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::kl_divergence_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::jensen_shannon_distance_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::dot_product_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::dot_similarity_batch_unit, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        vector::cosine_similarity_batch_prenorm,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_topk, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::cosine_argmax, m)?)?;
+    m.add_function(wrap_pyfunction!(vector::mmr_rerank, m)?)?;
+    m.add_class::<vector::VectorStore>()?;
+    #[cfg(feature = "hnsw")]
+    m.add_class::<hnsw::HnswIndex>()?;
+    m.add_class::<pq::ProductQuantizer>()?;
 
     // Decay math
     m.add_function(wrap_pyfunction!(decay::calculate_decayed_strength, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::calculate_decayed_strength_at, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        decay::calculate_decayed_strength_batch,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(decay::decayed_relevance, m)?)?;
+    m.add_function(wrap_pyfunction!(
+        decay::calculate_decayed_strength_powerlaw,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(
+        decay::calculate_decayed_strength_linear,
+        m
+    )?)?;
+    m.add_function(wrap_pyfunction!(decay::days_until_threshold, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::half_life_days, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::simulate_decay, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::adaptive_decay_rate, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::reinforce_and_decay, m)?)?;
     m.add_function(wrap_pyfunction!(decay::decay_traces_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::decay_traces_batch_at, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::decay_traces_batch_per_item, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::decay_and_flag_weak, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::consolidate_traces, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::merge_traces, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::merge_traces_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(decay::ema_update, m)?)?;
+    m.add_class::<decay::DecayTable>()?;
 
     // Scoring
     m.add_function(wrap_pyfunction!(scoring::bm25_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_score_batch_with_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_score_batch_boosted, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_proximity_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_fuzzy_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_score_batch_with_idf, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::compute_idf, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::document_frequencies, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_score_multi_query, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_topk, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_score_weighted, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25_plus_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25l_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::bm25f_score_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tfidf_score_batch, m)?)?;
+    m.add_class::<scoring::CorpusStats>()?;
+    m.add_class::<scoring::Bm25Index>()?;
     m.add_function(wrap_pyfunction!(scoring::tokenize, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tokenize_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tokenize_with_positions, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::best_snippet, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::nfkc_normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tokenize_filtered, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tokenize_config, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::tokenize_lang, m)?)?;
+    #[cfg(feature = "stemming")]
+    m.add_function(wrap_pyfunction!(scoring::tokenize_stemmed, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::char_ngrams, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::word_ngrams, m)?)?;
+    m.add_function(wrap_pyfunction!(scoring::term_freq_vector, m)?)?;
+
+    // Score fusion
+    m.add_function(wrap_pyfunction!(fusion::reciprocal_rank_fusion, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::weighted_fusion, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::minmax_normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::zscore_normalize, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::softmax, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::merge_topk, m)?)?;
+    m.add_function(wrap_pyfunction!(fusion::hybrid_score, m)?)?;
 
     Ok(())
 }
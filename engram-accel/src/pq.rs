@@ -0,0 +1,232 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+/// Product quantizer: vectors are split into `m_subspaces` equal slices and
+/// each slice is replaced by the index of its nearest k-means centroid, so a
+/// vector compresses to `m_subspaces` bytes.
+#[pyclass]
+pub struct ProductQuantizer {
+    dim: usize,
+    sub_dim: usize,
+    /// codebooks[subspace][centroid] is a `sub_dim`-long centroid.
+    codebooks: Vec<Vec<Vec<f32>>>,
+}
+
+#[pymethods]
+impl ProductQuantizer {
+    #[new]
+    fn new() -> Self {
+        Self {
+            dim: 0,
+            sub_dim: 0,
+            codebooks: Vec::new(),
+        }
+    }
+
+    /// Learn one k-means codebook of `n_centroids` per subspace.
+    ///
+    /// Raises ValueError if the vectors are empty or of mixed dimension, if the
+    /// dimension is not divisible by `m_subspaces`, or if `n_centroids` is not
+    /// in 1..=256 or exceeds the number of training vectors.
+    #[pyo3(signature = (vectors, m_subspaces, n_centroids, n_iter=25))]
+    fn train(
+        &mut self,
+        py: Python<'_>,
+        vectors: Vec<Vec<f32>>,
+        m_subspaces: usize,
+        n_centroids: usize,
+        n_iter: usize,
+    ) -> PyResult<()> {
+        let dim = vectors.first().map_or(0, Vec::len);
+        if dim == 0 {
+            return Err(PyValueError::new_err(
+                "need at least one non-empty training vector",
+            ));
+        }
+        if let Some(i) = vectors.iter().position(|v| v.len() != dim) {
+            return Err(PyValueError::new_err(format!(
+                "vector {i} has dimension {}, expected {dim}",
+                vectors[i].len()
+            )));
+        }
+        if m_subspaces == 0 || !dim.is_multiple_of(m_subspaces) {
+            return Err(PyValueError::new_err(format!(
+                "dimension {dim} is not divisible by m_subspaces={m_subspaces}"
+            )));
+        }
+        if n_centroids == 0 || n_centroids > 256 {
+            return Err(PyValueError::new_err(format!(
+                "n_centroids must be in 1..=256 to fit a u8 code, got {n_centroids}"
+            )));
+        }
+        if n_centroids > vectors.len() {
+            return Err(PyValueError::new_err(format!(
+                "n_centroids={n_centroids} exceeds the {} training vectors",
+                vectors.len()
+            )));
+        }
+
+        let sub_dim = dim / m_subspaces;
+        let codebooks = py.allow_threads(|| {
+            (0..m_subspaces)
+                .into_par_iter()
+                .map(|s| {
+                    let slices: Vec<&[f32]> = vectors
+                        .iter()
+                        .map(|v| &v[s * sub_dim..(s + 1) * sub_dim])
+                        .collect();
+                    kmeans(&slices, n_centroids, n_iter)
+                })
+                .collect()
+        });
+
+        self.dim = dim;
+        self.sub_dim = sub_dim;
+        self.codebooks = codebooks;
+        Ok(())
+    }
+
+    /// Encode each vector as one centroid index per subspace.
+    /// Raises ValueError if untrained or a vector has the wrong dimension.
+    fn encode(&self, vectors: Vec<Vec<f32>>) -> PyResult<Vec<Vec<u8>>> {
+        self.check_trained()?;
+        for v in &vectors {
+            self.check_dim(v)?;
+        }
+        Ok(vectors
+            .par_iter()
+            .map(|v| {
+                self.codebooks
+                    .iter()
+                    .zip(v.chunks_exact(self.sub_dim))
+                    .map(|(codebook, slice)| nearest_centroid(codebook, slice) as u8)
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Squared L2 distance from an uncompressed query to each encoded vector.
+    ///
+    /// Query-to-centroid distances are tabulated once per subspace, so each
+    /// code costs `m_subspaces` lookups. Raises ValueError if untrained, the
+    /// query has the wrong dimension, or a code is malformed.
+    fn asymmetric_distance(&self, query: Vec<f32>, codes: Vec<Vec<u8>>) -> PyResult<Vec<f32>> {
+        self.check_trained()?;
+        self.check_dim(&query)?;
+
+        let table: Vec<Vec<f32>> = self
+            .codebooks
+            .iter()
+            .zip(query.chunks_exact(self.sub_dim))
+            .map(|(codebook, slice)| codebook.iter().map(|c| squared_l2(c, slice)).collect())
+            .collect();
+
+        for (i, code) in codes.iter().enumerate() {
+            if code.len() != table.len() {
+                return Err(PyValueError::new_err(format!(
+                    "code {i} has {} entries, expected {}",
+                    code.len(),
+                    table.len()
+                )));
+            }
+            if let Some(&c) = code.iter().find(|&&c| c as usize >= table[0].len()) {
+                return Err(PyValueError::new_err(format!(
+                    "code {i} references centroid {c}, codebooks have {}",
+                    table[0].len()
+                )));
+            }
+        }
+
+        Ok(codes
+            .par_iter()
+            .map(|code| {
+                code.iter()
+                    .zip(&table)
+                    .map(|(&c, dists)| dists[c as usize])
+                    .sum()
+            })
+            .collect())
+    }
+}
+
+impl ProductQuantizer {
+    fn check_trained(&self) -> PyResult<()> {
+        if self.codebooks.is_empty() {
+            return Err(PyValueError::new_err(
+                "quantizer is not trained, call train() first",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_dim(&self, vector: &[f32]) -> PyResult<()> {
+        if vector.len() != self.dim {
+            return Err(PyValueError::new_err(format!(
+                "vector has dimension {}, quantizer was trained on {}",
+                vector.len(),
+                self.dim
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Lloyd's k-means seeded with evenly strided samples, so training is deterministic.
+fn kmeans(points: &[&[f32]], k: usize, n_iter: usize) -> Vec<Vec<f32>> {
+    let dim = points[0].len();
+    let mut centroids: Vec<Vec<f32>> = (0..k)
+        .map(|c| points[c * points.len() / k].to_vec())
+        .collect();
+    let mut assignment = vec![usize::MAX; points.len()];
+
+    for _ in 0..n_iter {
+        let mut changed = false;
+        for (slot, point) in assignment.iter_mut().zip(points) {
+            let nearest = nearest_centroid(&centroids, point);
+            if *slot != nearest {
+                *slot = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![vec![0.0_f64; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (&c, point) in assignment.iter().zip(points) {
+            counts[c] += 1;
+            for (acc, &x) in sums[c].iter_mut().zip(point.iter()) {
+                *acc += x as f64;
+            }
+        }
+        // Empty clusters keep their previous centroid.
+        for ((centroid, sum), &count) in centroids.iter_mut().zip(&sums).zip(&counts) {
+            if count > 0 {
+                for (x, &s) in centroid.iter_mut().zip(sum) {
+                    *x = (s / count as f64) as f32;
+                }
+            }
+        }
+    }
+
+    centroids
+}
+
+fn nearest_centroid(centroids: &[Vec<f32>], point: &[f32]) -> usize {
+    let mut best = 0;
+    let mut best_dist = f32::INFINITY;
+    for (i, c) in centroids.iter().enumerate() {
+        let d = squared_l2(c, point);
+        if d < best_dist {
+            best = i;
+            best_dist = d;
+        }
+    }
+    best
+}
+
+fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
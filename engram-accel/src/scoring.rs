@@ -1,5 +1,11 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::topk::TopK;
+use crate::vector::{map_range, topk_rows, PARALLEL_THRESHOLD};
 
 /// Tokenize text: lowercase and split on non-alphanumeric boundaries.
 #[pyfunction]
@@ -22,74 +28,1228 @@ pub fn tokenize(text: &str) -> Vec<String> {
     tokens
 }
 
+/// `tokenize` with switches to keep some compounds whole, then drop tokens
+/// shorter than `min_len` characters. `keep_hyphens` keeps a hyphen between
+/// two word characters ("covid-19"); `keep_dots_in_numbers` keeps a dot
+/// between two digits ("3.14"). With both off and `min_len` at most 1 the
+/// output matches `tokenize`.
+#[pyfunction]
+pub fn tokenize_config(
+    text: &str,
+    keep_hyphens: bool,
+    keep_dots_in_numbers: bool,
+    min_len: usize,
+) -> Vec<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    let is_word = |ch: char| ch.is_alphanumeric() || ch == '_';
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+
+    for (i, &ch) in chars.iter().enumerate() {
+        let prev = current.chars().next_back();
+        let next = chars.get(i + 1).copied();
+        let joins = match ch {
+            '-' => keep_hyphens && prev.is_some() && next.is_some_and(is_word),
+            '.' => {
+                keep_dots_in_numbers
+                    && prev.is_some_and(|c| c.is_ascii_digit())
+                    && next.is_some_and(|c| c.is_ascii_digit())
+            }
+            _ => false,
+        };
+        if is_word(ch) || joins {
+            current.push(ch);
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens.retain(|t| t.chars().count() >= min_len);
+    tokens
+}
+
+/// `tokenize` over many texts in one call, in input order. Large batches are
+/// split across threads with rayon, with the GIL released meanwhile.
+#[pyfunction]
+pub fn tokenize_batch(py: Python<'_>, texts: Vec<String>) -> Vec<Vec<String>> {
+    py.allow_threads(move || {
+        if texts.len() < PARALLEL_THRESHOLD {
+            texts.iter().map(|t| tokenize(t)).collect()
+        } else {
+            texts.par_iter().map(|t| tokenize(t)).collect()
+        }
+    })
+}
+
+/// Language-aware `tokenize`. For `"zh"` and `"ja"`, runs of Han/kana
+/// characters (which have no spaces to split on) become overlapping character
+/// bigrams, or a single character for one-character runs; other runs and every
+/// other language use the plain `tokenize` split.
+#[pyfunction]
+pub fn tokenize_lang(text: &str, lang: &str) -> Vec<String> {
+    let tokens = tokenize(text);
+    if !matches!(lang, "zh" | "ja") {
+        return tokens;
+    }
+
+    let mut out = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let chars: Vec<char> = token.chars().collect();
+        for run in chars.chunk_by(|a, b| is_cjk(*a) == is_cjk(*b)) {
+            if !is_cjk(run[0]) {
+                out.push(run.iter().collect());
+            } else if run.len() == 1 {
+                out.push(run[0].to_string());
+            } else {
+                out.extend(run.windows(2).map(|w| w.iter().collect::<String>()));
+            }
+        }
+    }
+    out
+}
+
+/// `tokenize`, pairing each token with its starting byte offset in `text`.
+#[pyfunction]
+pub fn tokenize_with_positions(text: &str) -> Vec<(String, usize)> {
+    token_spans(text)
+        .into_iter()
+        .map(|(token, start, _)| (token, start))
+        .collect()
+}
+
+/// The window of `window` tokens with the most query-term hits, as
+/// (snippet, start, end) where `text[start..end]` is the snippet. Ties go to
+/// the earliest window; text with no tokens yields an empty snippet.
+/// Query terms go through `tokenize` too, so they match the text's tokens.
+#[pyfunction]
+pub fn best_snippet(text: &str, query_terms: Vec<String>, window: usize) -> (String, usize, usize) {
+    let spans = token_spans(text);
+    if spans.is_empty() || window == 0 {
+        return (String::new(), 0, 0);
+    }
+
+    let terms: HashSet<String> = query_terms.iter().flat_map(|t| tokenize(t)).collect();
+    let hits: Vec<usize> = spans
+        .iter()
+        .map(|(token, _, _)| usize::from(terms.contains(token)))
+        .collect();
+
+    let window = window.min(spans.len());
+    let mut count: usize = hits[..window].iter().sum();
+    let (mut best_start, mut best_count) = (0, count);
+    for start in 1..=spans.len() - window {
+        count = count + hits[start + window - 1] - hits[start - 1];
+        if count > best_count {
+            best_start = start;
+            best_count = count;
+        }
+    }
+
+    let start = spans[best_start].1;
+    let end = spans[best_start + window - 1].2;
+    (text[start..end].to_string(), start, end)
+}
+
+/// Unicode NFKC normalization, so compatibility forms ("ﬁ", full-width
+/// letters) match their plain equivalents. Apply before `tokenize`.
+#[pyfunction]
+pub fn nfkc_normalize(text: &str) -> String {
+    text.nfkc().collect()
+}
+
+/// `tokenize`, then drop stopwords and tokens shorter than `min_len` characters.
+/// Stopwords are lowercased before matching, so their case does not matter.
+#[pyfunction]
+pub fn tokenize_filtered(text: &str, stopwords: Vec<String>, min_len: usize) -> Vec<String> {
+    let stopwords: HashSet<String> = stopwords.iter().map(|w| w.to_lowercase()).collect();
+    tokenize(text)
+        .into_iter()
+        .filter(|t| t.chars().count() >= min_len && !stopwords.contains(t))
+        .collect()
+}
+
+/// `tokenize`, then reduce each token to its English (Snowball/Porter2) stem
+/// so "running" and "runs" both match "run".
+#[cfg(feature = "stemming")]
+#[pyfunction]
+pub fn tokenize_stemmed(text: &str) -> Vec<String> {
+    let stemmer = rust_stemmers::Stemmer::create(rust_stemmers::Algorithm::English);
+    tokenize(text)
+        .iter()
+        .map(|t| stemmer.stem(t).into_owned())
+        .collect()
+}
+
+/// Overlapping character n-grams of the lowercased text.
+/// Returns an empty vector when `n` is 0 or the text has fewer than `n` characters.
+#[pyfunction]
+pub fn char_ngrams(text: &str, n: usize) -> Vec<String> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    chars.windows(n).map(|w| w.iter().collect()).collect()
+}
+
+/// Overlapping word n-grams, each joined with a single space.
+/// Returns an empty vector when `n` is 0 or there are fewer than `n` tokens.
+#[pyfunction]
+pub fn word_ngrams(tokens: Vec<String>, n: usize) -> Vec<String> {
+    if n == 0 {
+        return Vec::new();
+    }
+    tokens.windows(n).map(|w| w.join(" ")).collect()
+}
+
+/// Raw term counts of `tokens` as a dense vector aligned to `vocab`, where
+/// `vocab[term]` is the term's position. The result has `len(vocab)` entries;
+/// out-of-vocabulary tokens are ignored. Raises ValueError if a position is
+/// not below the vocabulary size.
+#[pyfunction]
+pub fn term_freq_vector(tokens: Vec<String>, vocab: HashMap<String, usize>) -> PyResult<Vec<f64>> {
+    if let Some((term, &pos)) = vocab.iter().find(|&(_, &pos)| pos >= vocab.len()) {
+        return Err(PyValueError::new_err(format!(
+            "vocab position {pos} for '{term}' is out of range for {} terms",
+            vocab.len()
+        )));
+    }
+    let mut counts = vec![0.0; vocab.len()];
+    for token in &tokens {
+        if let Some(&pos) = vocab.get(token) {
+            counts[pos] += 1.0;
+        }
+    }
+    Ok(counts)
+}
+
 /// BM25 scoring for N documents against a single query.
 ///
 /// Each document is a Vec<String> of pre-tokenized terms.
 /// Returns a Vec<f64> of BM25 scores, one per document.
+/// The GIL is released while scoring, and large batches are scored in
+/// parallel with rayon; output order matches input.
+///
+/// `idf_variant` picks the IDF formula, with N = `total_docs` and df the
+/// document frequency:
+/// - `"bm25"` (default): the smoothed `ln((N - df + 0.5) / (df + 0.5) + 1)`
+///   used by every other BM25 function here. It only goes negative when df
+///   exceeds N, i.e. when `total_docs` undercounts the corpus.
+/// - `"bm25_nonneg"`: `"bm25"` clamped at 0.0.
+/// - `"lucene"`: Lucene/Elasticsearch's `ln(1 + (N - df + 0.5) / (df + 0.5))`,
+///   algebraically the same as `"bm25"`; accepted so callers can name it.
 #[pyfunction]
+#[pyo3(signature = (query_terms, documents, total_docs, avg_doc_len, k1, b, idf_variant="bm25"))]
+#[allow(clippy::too_many_arguments)]
 pub fn bm25_score_batch(
+    py: Python<'_>,
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    idf_variant: &str,
+) -> PyResult<Vec<f64>> {
+    let variant = IdfVariant::parse(idf_variant)?;
+    Ok(py.allow_threads(move || {
+        bm25_scores(
+            &query_terms,
+            &documents,
+            total_docs,
+            avg_doc_len,
+            k1,
+            b,
+            variant,
+        )
+    }))
+}
+
+/// Best `k` BM25 matches as (index, score), best first, ties broken by the
+/// smaller index. Each document is scored straight into a bounded heap that
+/// keeps only `k` candidates, so no full score vector is built. The result is
+/// `bm25_score_batch` sorted and cut to `k`, so 0.0-scoring documents only
+/// fill in when fewer than `k` documents match. The GIL is released while
+/// scoring, and large batches are scored in parallel.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub fn bm25_topk(
+    py: Python<'_>,
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    k: usize,
+) -> Vec<(usize, f64)> {
+    py.allow_threads(move || {
+        let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+        let avg_doc_len = if avg_doc_len == 0.0 { 1.0 } else { avg_doc_len };
+        topk_rows(&documents, k, None, |doc| {
+            let term_freq = term_freqs_of(doc);
+            doc_score(
+                &query_terms,
+                doc,
+                &term_freq,
+                avg_doc_len,
+                k1,
+                b,
+                0.0,
+                |_, term| idf.get(term).copied().unwrap_or(0.0),
+            )
+        })
+    })
+}
+
+/// BM25 plus a proximity bonus for documents where the query terms sit close together.
+///
+/// Documents are `(token, position)` lists as from `tokenize_with_positions`;
+/// tokens are ordered by position. A document containing every distinct query
+/// term gains `proximity_weight / span`, where `span` is the length in tokens of
+/// the shortest window covering all of them. Other documents get plain BM25.
+#[pyfunction]
+pub fn bm25_proximity_score_batch(
+    query_terms: Vec<String>,
+    documents_with_positions: Vec<Vec<(String, usize)>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    proximity_weight: f64,
+) -> Vec<f64> {
+    let documents: Vec<Vec<String>> = documents_with_positions
+        .into_iter()
+        .map(|mut doc| {
+            doc.sort_by_key(|&(_, pos)| pos);
+            doc.into_iter().map(|(token, _)| token).collect()
+        })
+        .collect();
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+    let mut scores = score_documents(
+        &query_terms,
+        &documents,
+        avg_doc_len,
+        k1,
+        b,
+        0.0,
+        |_, term| idf.get(term).copied().unwrap_or(0.0),
+    );
+
+    let terms: HashSet<&str> = query_terms.iter().map(String::as_str).collect();
+    for (score, doc) in scores.iter_mut().zip(&documents) {
+        if let Some(span) = min_cover_span(doc, &terms) {
+            *score += proximity_weight / span as f64;
+        }
+    }
+
+    scores
+}
+
+/// `bm25_score_batch` with `total_docs` and `avg_doc_len` read from a
+/// `CorpusStats` tracker instead of passed in.
+#[pyfunction]
+pub fn bm25_score_batch_with_stats(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    stats: PyRef<'_, CorpusStats>,
+    k1: f64,
+    b: f64,
+) -> Vec<f64> {
+    bm25_scores(
+        &query_terms,
+        &documents,
+        stats.total_docs,
+        stats.avg_doc_len(),
+        k1,
+        b,
+        IdfVariant::Bm25,
+    )
+}
+
+/// `bm25_score_batch` with each document's score multiplied by its boost
+/// (editorial, freshness, ...). Raises ValueError unless `boosts` has one
+/// entry per document.
+#[pyfunction]
+pub fn bm25_score_batch_boosted(
     query_terms: Vec<String>,
     documents: Vec<Vec<String>>,
     total_docs: usize,
     avg_doc_len: f64,
     k1: f64,
     b: f64,
+    boosts: Vec<f64>,
+) -> PyResult<Vec<f64>> {
+    if boosts.len() != documents.len() {
+        return Err(PyValueError::new_err(format!(
+            "boosts has length {} but there are {} documents",
+            boosts.len(),
+            documents.len()
+        )));
+    }
+
+    let mut scores = bm25_scores(
+        &query_terms,
+        &documents,
+        total_docs,
+        avg_doc_len,
+        k1,
+        b,
+        IdfVariant::Bm25,
+    );
+    for (score, boost) in scores.iter_mut().zip(&boosts) {
+        *score *= boost;
+    }
+    Ok(scores)
+}
+
+/// Typo-tolerant BM25: document terms within `max_edit_distance` (Levenshtein)
+/// of a query term also count toward its frequency, down-weighted to
+/// `1 / (1 + distance)` of an exact hit. Document frequency uses the same
+/// fuzzy match.
+///
+/// Every query term is compared against every distinct term of every
+/// document, so the cost grows with vocabulary size; `max_edit_distance` is
+/// capped at 2 to keep that bounded (terms whose lengths differ by more than
+/// the cap are skipped without computing a distance).
+#[pyfunction]
+pub fn bm25_fuzzy_score_batch(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    max_edit_distance: usize,
 ) -> Vec<f64> {
     if query_terms.is_empty() || documents.is_empty() {
         return vec![0.0; documents.len()];
     }
 
+    let max_dist = max_edit_distance.min(2);
+    let avg_doc_len = if avg_doc_len == 0.0 { 1.0 } else { avg_doc_len };
+    let query: Vec<Vec<char>> = query_terms.iter().map(|t| t.chars().collect()).collect();
+
+    // fuzzy_tf[doc][i]: down-weighted frequency of query term i in doc
+    let fuzzy_tf: Vec<Vec<f64>> = doc_term_freqs(&documents)
+        .iter()
+        .map(|term_freq| {
+            let mut tfs = vec![0.0; query.len()];
+            for (&term, &tf) in term_freq {
+                let term: Vec<char> = term.chars().collect();
+                for (acc, q) in tfs.iter_mut().zip(&query) {
+                    if let Some(d) = bounded_levenshtein(q, &term, max_dist) {
+                        *acc += tf as f64 / (1.0 + d as f64);
+                    }
+                }
+            }
+            tfs
+        })
+        .collect();
+
     let total_docs_f = total_docs as f64;
+    let idf: Vec<f64> = (0..query.len())
+        .map(|i| {
+            let df = fuzzy_tf.iter().filter(|tfs| tfs[i] > 0.0).count();
+            bm25_idf(total_docs_f, df as f64)
+        })
+        .collect();
+
+    documents
+        .iter()
+        .zip(&fuzzy_tf)
+        .map(|(doc, tfs)| {
+            let doc_len = doc.len() as f64;
+            tfs.iter()
+                .zip(&idf)
+                .filter(|(&tf, _)| tf > 0.0)
+                .fold(0.0, |score, (&tf, &idf)| {
+                    score
+                        + idf * (tf * (k1 + 1.0))
+                            / (tf + k1 * (1.0 - b + b * doc_len / avg_doc_len))
+                })
+        })
+        .collect()
+}
+
+/// BM25 scores of several queries against the same documents.
+///
+/// Document term frequencies are built once and shared by every query.
+/// Returns one row of per-document scores for each query.
+#[pyfunction]
+pub fn bm25_score_multi_query(
+    queries: Vec<Vec<String>>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+) -> Vec<Vec<f64>> {
+    let idf = corpus_idf(
+        queries.iter().flatten(),
+        &documents,
+        total_docs,
+        IdfVariant::Bm25,
+    );
+    let term_freqs = doc_term_freqs(&documents);
+    queries
+        .iter()
+        .map(|query_terms| {
+            score_term_freqs(
+                query_terms,
+                &documents,
+                &term_freqs,
+                avg_doc_len,
+                k1,
+                b,
+                0.0,
+                |_, term| idf.get(term).copied().unwrap_or(0.0),
+            )
+        })
+        .collect()
+}
+
+/// BM25 scoring with a weight per query term, multiplied into that term's
+/// contribution (e.g. original terms above query-expansion synonyms).
+/// Raises ValueError if `query_weights` and `query_terms` differ in length.
+#[pyfunction]
+pub fn bm25_score_weighted(
+    query_terms: Vec<String>,
+    query_weights: Vec<f64>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+) -> PyResult<Vec<f64>> {
+    if query_weights.len() != query_terms.len() {
+        return Err(PyValueError::new_err(format!(
+            "query_terms has {} entries but query_weights has {}",
+            query_terms.len(),
+            query_weights.len()
+        )));
+    }
+    if query_terms.is_empty() || documents.is_empty() {
+        return Ok(vec![0.0; documents.len()]);
+    }
+
+    let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+    Ok(score_documents(
+        &query_terms,
+        &documents,
+        avg_doc_len,
+        k1,
+        b,
+        0.0,
+        |i, term| query_weights[i] * idf.get(term).copied().unwrap_or(0.0),
+    ))
+}
+
+/// BM25+ scoring: BM25 with a `delta` lower bound on each present term's TF
+/// component, so long documents are not driven toward zero (default delta 1.0).
+#[pyfunction]
+#[pyo3(signature = (query_terms, documents, total_docs, avg_doc_len, k1, b, delta=1.0))]
+pub fn bm25_plus_score_batch(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    delta: f64,
+) -> Vec<f64> {
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+    score_documents(
+        &query_terms,
+        &documents,
+        avg_doc_len,
+        k1,
+        b,
+        delta,
+        |_, term| idf.get(term).copied().unwrap_or(0.0),
+    )
+}
+
+/// BM25L scoring, which shifts the length-normalized term frequency
+/// `c = tf / (1 - b + b * dl / avgdl)` by `delta` before saturation,
+/// `(k1 + 1) * (c + delta) / (k1 + c + delta)`, so long documents are not
+/// over-penalized (default delta 0.5).
+#[pyfunction]
+#[pyo3(signature = (query_terms, documents, total_docs, avg_doc_len, k1, b, delta=0.5))]
+pub fn bm25l_score_batch(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    delta: f64,
+) -> Vec<f64> {
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
     let avg_doc_len = if avg_doc_len == 0.0 { 1.0 } else { avg_doc_len };
+    let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+    documents
+        .iter()
+        .zip(doc_term_freqs(&documents))
+        .map(|(doc, term_freq)| {
+            let norm = 1.0 - b + b * doc.len() as f64 / avg_doc_len;
+            let mut score = 0.0_f64;
+            for term in &query_terms {
+                let Some(&tf) = term_freq.get(term.as_str()) else {
+                    continue;
+                };
+                let c = tf as f64 / norm + delta;
+                score += idf.get(term.as_str()).copied().unwrap_or(0.0) * (k1 + 1.0) * c / (k1 + c);
+            }
+            score
+        })
+        .collect()
+}
+
+/// BM25 scoring against a caller-supplied IDF table instead of document
+/// frequencies counted from `documents`, e.g. global IDF for a candidate set.
+/// Query terms missing from `idf` contribute nothing.
+#[pyfunction]
+pub fn bm25_score_batch_with_idf(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    idf: HashMap<String, f64>,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+) -> Vec<f64> {
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    score_documents(
+        &query_terms,
+        &documents,
+        avg_doc_len,
+        k1,
+        b,
+        0.0,
+        |_, term| idf.get(term).copied().unwrap_or(0.0),
+    )
+}
+
+/// Number of `documents` containing each distinct term (document frequency).
+/// A term repeated within one document still counts that document once.
+#[pyfunction]
+pub fn document_frequencies(documents: Vec<Vec<String>>) -> HashMap<String, usize> {
+    count_doc_freqs(&documents)
+        .into_iter()
+        .map(|(term, df)| (term.to_string(), df))
+        .collect()
+}
+
+/// Smoothed BM25 IDF of every distinct term in `documents`, as a term → IDF
+/// map suitable for caching and passing to `bm25_score_batch_with_idf`.
+#[pyfunction]
+pub fn compute_idf(documents: Vec<Vec<String>>, total_docs: usize) -> HashMap<String, f64> {
+    let total_docs = total_docs as f64;
+    count_doc_freqs(&documents)
+        .into_iter()
+        .map(|(term, df)| (term.to_string(), bm25_idf(total_docs, df as f64)))
+        .collect()
+}
+
+/// Classic TF-IDF scoring: sum over query terms of raw term frequency times
+/// the same smoothed IDF that BM25 uses, with no saturation or length norm.
+#[pyfunction]
+pub fn tfidf_score_batch(
+    query_terms: Vec<String>,
+    documents: Vec<Vec<String>>,
+    total_docs: usize,
+) -> Vec<f64> {
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
 
-    // Build document frequency: how many docs contain each query term
+    let idf = corpus_idf(&query_terms, &documents, total_docs, IdfVariant::Bm25);
+    doc_term_freqs(&documents)
+        .iter()
+        .map(|term_freq| {
+            query_terms
+                .iter()
+                .filter_map(|term| {
+                    let tf = *term_freq.get(term.as_str())? as f64;
+                    Some(tf * idf.get(term.as_str()).copied().unwrap_or(0.0))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// BM25F scoring for N multi-field documents against a single query.
+///
+/// `fields[doc][field]` is the token list of one field. Per-field term
+/// frequencies are length-normalized against `field_avg_lens`, weighted by
+/// `field_boosts` and summed before the BM25 saturation is applied once.
+/// Raises ValueError if a document's field count differs from the boost or
+/// average-length counts.
+#[pyfunction]
+pub fn bm25f_score_batch(
+    query_terms: Vec<String>,
+    fields: Vec<Vec<Vec<String>>>,
+    field_boosts: Vec<f64>,
+    field_avg_lens: Vec<f64>,
+    total_docs: usize,
+    k1: f64,
+    b: f64,
+) -> PyResult<Vec<f64>> {
+    let n_fields = field_boosts.len();
+    if field_avg_lens.len() != n_fields {
+        return Err(PyValueError::new_err(format!(
+            "field_boosts has {n_fields} entries but field_avg_lens has {}",
+            field_avg_lens.len()
+        )));
+    }
+    if let Some((i, doc)) = fields
+        .iter()
+        .enumerate()
+        .find(|(_, doc)| doc.len() != n_fields)
+    {
+        return Err(PyValueError::new_err(format!(
+            "document {i} has {} fields, expected {n_fields}",
+            doc.len()
+        )));
+    }
+    if query_terms.is_empty() || fields.is_empty() {
+        return Ok(vec![0.0; fields.len()]);
+    }
+
+    let total_docs_f = total_docs as f64;
+    let avg_lens: Vec<f64> = field_avg_lens
+        .iter()
+        .map(|&avg| if avg == 0.0 { 1.0 } else { avg })
+        .collect();
+
+    // Document frequency counts a doc once if any of its fields has the term
     let mut doc_freq: HashMap<&str, usize> = HashMap::new();
     for term in &query_terms {
-        let mut count = 0usize;
-        for doc in &documents {
-            if doc.iter().any(|t| t == term) {
-                count += 1;
+        let count = fields
+            .iter()
+            .filter(|doc| doc.iter().any(|field| field.iter().any(|t| t == term)))
+            .count();
+        doc_freq.insert(term.as_str(), count);
+    }
+
+    let mut scores = Vec::with_capacity(fields.len());
+
+    for doc in &fields {
+        // Boosted, length-normalized term frequency summed over fields
+        let mut weighted_tf: HashMap<&str, f64> = HashMap::new();
+        for ((field, &boost), &avg_len) in doc.iter().zip(&field_boosts).zip(&avg_lens) {
+            if field.is_empty() {
+                continue;
+            }
+            let norm = 1.0 - b + b * field.len() as f64 / avg_len;
+            for t in field {
+                *weighted_tf.entry(t.as_str()).or_insert(0.0) += boost / norm;
             }
         }
-        doc_freq.insert(term.as_str(), count);
+
+        let mut score = 0.0_f64;
+
+        for term in &query_terms {
+            let tf = match weighted_tf.get(term.as_str()) {
+                Some(&f) => f,
+                None => continue,
+            };
+
+            let df = *doc_freq.get(term.as_str()).unwrap_or(&1) as f64;
+            let idf = bm25_idf(total_docs_f, df);
+
+            score += idf * (tf * (k1 + 1.0)) / (tf + k1);
+        }
+
+        scores.push(score);
+    }
+
+    Ok(scores)
+}
+
+/// Han ideographs and Japanese kana, which are written without word spaces.
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}'   // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}'   // CJK compatibility ideographs
+        | '\u{FF66}'..='\u{FF9F}'   // Half-width katakana
+        | '\u{20000}'..='\u{2FA1F}' // CJK extensions B onward
+    )
+}
+
+/// `tokenize` tokens with the byte range each one covers in `text`.
+///
+/// Splitting runs over `text.to_lowercase()` exactly as in `tokenize`, so the
+/// tokens match even where lowercasing is contextual (final sigma) or expands a
+/// character ("İ" becomes "i" plus a combining dot). Every lowercased char is
+/// traced back to the original char it came from to recover the byte range.
+fn token_spans(text: &str) -> Vec<(String, usize, usize)> {
+    let lower = text.to_lowercase();
+    // str::to_lowercase emits as many chars per source char as char::to_lowercase.
+    let sources = text.char_indices().flat_map(|(pos, ch)| {
+        std::iter::repeat_n((pos, pos + ch.len_utf8()), ch.to_lowercase().count())
+    });
+
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let (mut start, mut end) = (0, 0);
+
+    for (ch, (src_start, src_end)) in lower.chars().zip(sources) {
+        if ch.is_alphanumeric() || ch == '_' {
+            if current.is_empty() {
+                start = src_start;
+            }
+            current.push(ch);
+            end = src_end;
+        } else if !current.is_empty() {
+            spans.push((std::mem::take(&mut current), start, end));
+        }
+    }
+    if !current.is_empty() {
+        spans.push((current, start, end));
     }
 
-    let mut scores = Vec::with_capacity(documents.len());
+    spans
+}
+
+/// Length in tokens of the shortest window of `doc` containing every term,
+/// or None if some term is missing (or there are no terms).
+fn min_cover_span(doc: &[String], terms: &HashSet<&str>) -> Option<usize> {
+    if terms.is_empty() {
+        return None;
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut covered = 0;
+    let mut best: Option<usize> = None;
+    let mut left = 0;
 
-    for doc in &documents {
-        if doc.is_empty() {
-            scores.push(0.0);
+    for (right, token) in doc.iter().enumerate() {
+        let Some(&term) = terms.get(token.as_str()) else {
             continue;
+        };
+        let count = counts.entry(term).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            covered += 1;
+        }
+
+        // Shrink from the left while every term stays covered.
+        while covered == terms.len() {
+            let span = right - left + 1;
+            best = Some(best.map_or(span, |b| b.min(span)));
+            if let Some(count) = counts.get_mut(doc[left].as_str()) {
+                *count -= 1;
+                if *count == 0 {
+                    covered -= 1;
+                }
+            }
+            left += 1;
+        }
+    }
+
+    best
+}
+
+/// Running corpus totals for streaming BM25, updated in O(1) per document.
+#[pyclass]
+pub struct CorpusStats {
+    #[pyo3(get)]
+    total_docs: usize,
+    #[pyo3(get)]
+    total_tokens: usize,
+}
+
+#[pymethods]
+impl CorpusStats {
+    #[new]
+    #[pyo3(signature = (total_docs=0, total_tokens=0))]
+    fn new(total_docs: usize, total_tokens: usize) -> Self {
+        Self {
+            total_docs,
+            total_tokens,
+        }
+    }
+
+    fn add_document(&mut self, token_count: usize) {
+        self.total_docs += 1;
+        self.total_tokens += token_count;
+    }
+
+    /// Raises ValueError if it would drive either total below zero.
+    fn remove_document(&mut self, token_count: usize) -> PyResult<()> {
+        if self.total_docs == 0 || token_count > self.total_tokens {
+            return Err(PyValueError::new_err(format!(
+                "cannot remove a {token_count}-token document from {} docs / {} tokens",
+                self.total_docs, self.total_tokens
+            )));
+        }
+        self.total_docs -= 1;
+        self.total_tokens -= token_count;
+        Ok(())
+    }
+
+    /// Mean tokens per document, 0.0 for an empty corpus.
+    fn avg_doc_len(&self) -> f64 {
+        if self.total_docs == 0 {
+            0.0
+        } else {
+            self.total_tokens as f64 / self.total_docs as f64
+        }
+    }
+}
+
+/// Persistent BM25 index that keeps document frequencies, document count and
+/// average length up to date as documents are added and removed, so queries
+/// never rescan the corpus.
+#[pyclass]
+pub struct Bm25Index {
+    k1: f64,
+    b: f64,
+    /// term -> (doc_id -> term frequency)
+    postings: HashMap<String, HashMap<String, usize>>,
+    /// doc_id -> (token count, distinct terms)
+    docs: HashMap<String, (usize, Vec<String>)>,
+    total_len: usize,
+}
+
+#[pymethods]
+impl Bm25Index {
+    #[new]
+    #[pyo3(signature = (k1=1.5, b=0.75))]
+    fn new(k1: f64, b: f64) -> Self {
+        Self {
+            k1,
+            b,
+            postings: HashMap::new(),
+            docs: HashMap::new(),
+            total_len: 0,
         }
+    }
+
+    /// Index `tokens` under `doc_id`, replacing any document already stored there.
+    fn add_document(&mut self, doc_id: String, tokens: Vec<String>) {
+        self.remove_document(&doc_id);
 
-        // Term frequencies in this document
         let mut term_freq: HashMap<&str, usize> = HashMap::new();
-        for t in doc {
+        for t in &tokens {
             *term_freq.entry(t.as_str()).or_insert(0) += 1;
         }
+        for (&term, &tf) in &term_freq {
+            self.postings
+                .entry(term.to_string())
+                .or_default()
+                .insert(doc_id.clone(), tf);
+        }
 
-        let doc_len = doc.len() as f64;
-        let mut score = 0.0_f64;
+        let terms = term_freq.keys().map(|t| t.to_string()).collect();
+        self.total_len += tokens.len();
+        self.docs.insert(doc_id, (tokens.len(), terms));
+    }
+
+    /// Drop `doc_id` from the index. Returns false if it was not indexed.
+    fn remove_document(&mut self, doc_id: &str) -> bool {
+        let Some((len, terms)) = self.docs.remove(doc_id) else {
+            return false;
+        };
+        for term in terms {
+            if let Some(posting) = self.postings.get_mut(&term) {
+                posting.remove(doc_id);
+                if posting.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        self.total_len -= len;
+        true
+    }
+
+    /// Best `k` documents for the query as (doc_id, score), best first,
+    /// ties broken by the smaller doc_id. Documents matching no term are omitted.
+    fn search(&self, query_terms: Vec<String>, k: usize) -> Vec<(String, f64)> {
+        if self.docs.is_empty() {
+            return Vec::new();
+        }
 
+        let total_docs = self.docs.len() as f64;
+        let avg_doc_len = self.total_len as f64 / total_docs;
+        let avg_doc_len = if avg_doc_len == 0.0 { 1.0 } else { avg_doc_len };
+
+        let mut scores: HashMap<&str, f64> = HashMap::new();
         for term in &query_terms {
-            let tf = match term_freq.get(term.as_str()) {
-                Some(&f) => f as f64,
-                None => continue,
+            let Some(posting) = self.postings.get(term) else {
+                continue;
             };
+            let idf = bm25_idf(total_docs, posting.len() as f64);
+            for (doc_id, &tf) in posting {
+                let tf = tf as f64;
+                let doc_len = self.docs[doc_id].0 as f64;
+                let tf_component = (tf * (self.k1 + 1.0))
+                    / (tf + self.k1 * (1.0 - self.b + self.b * doc_len / avg_doc_len));
+                *scores.entry(doc_id.as_str()).or_insert(0.0) += idf * tf_component;
+            }
+        }
 
-            let df = *doc_freq.get(term.as_str()).unwrap_or(&1) as f64;
+        let mut top = TopK::new(k);
+        for (doc_id, score) in scores {
+            top.push(doc_id, score);
+        }
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|(doc_id, score)| (doc_id.to_string(), score))
+            .collect()
+    }
 
-            // IDF with smoothing
-            let idf = ((total_docs_f - df + 0.5) / (df + 0.5) + 1.0).ln();
+    fn __len__(&self) -> usize {
+        self.docs.len()
+    }
+}
 
-            // TF with saturation and length normalization
-            let tf_component = (tf * (k1 + 1.0)) / (tf + k1 * (1.0 - b + b * doc_len / avg_doc_len));
+/// Levenshtein distance between `a` and `b`, or None once it exceeds `max`.
+fn bounded_levenshtein(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, &ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        if curr.iter().min().is_some_and(|&m| m > max) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    let dist = prev[b.len()];
+    (dist <= max).then_some(dist)
+}
 
-            score += idf * tf_component;
+/// IDF with smoothing, shared by the BM25 variants.
+fn bm25_idf(total_docs: f64, doc_freq: f64) -> f64 {
+    ((total_docs - doc_freq + 0.5) / (doc_freq + 0.5) + 1.0).ln()
+}
+
+/// IDF formula selected by `bm25_score_batch`'s `idf_variant`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IdfVariant {
+    /// `bm25_idf`, the default.
+    Bm25,
+    /// `Bm25` clamped at 0.0.
+    Bm25NonNeg,
+    /// Lucene's spelling of `bm25_idf`.
+    Lucene,
+}
+
+impl IdfVariant {
+    fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "bm25" => Ok(IdfVariant::Bm25),
+            "bm25_nonneg" => Ok(IdfVariant::Bm25NonNeg),
+            "lucene" => Ok(IdfVariant::Lucene),
+            other => Err(PyValueError::new_err(format!(
+                "unknown idf_variant '{other}', expected 'bm25', 'bm25_nonneg' or 'lucene'"
+            ))),
         }
+    }
 
-        scores.push(score);
+    fn idf(self, total_docs: f64, doc_freq: f64) -> f64 {
+        match self {
+            IdfVariant::Bm25 => bm25_idf(total_docs, doc_freq),
+            IdfVariant::Bm25NonNeg => bm25_idf(total_docs, doc_freq).max(0.0),
+            IdfVariant::Lucene => (1.0 + (total_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln(),
+        }
     }
+}
 
-    scores
+/// Body of `bm25_score_batch`, callable without the GIL.
+pub(crate) fn bm25_scores(
+    query_terms: &[String],
+    documents: &[Vec<String>],
+    total_docs: usize,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    idf_variant: IdfVariant,
+) -> Vec<f64> {
+    if query_terms.is_empty() || documents.is_empty() {
+        return vec![0.0; documents.len()];
+    }
+
+    let idf = corpus_idf(query_terms, documents, total_docs, idf_variant);
+    score_documents(
+        query_terms,
+        documents,
+        avg_doc_len,
+        k1,
+        b,
+        0.0,
+        |_, term| idf.get(term).copied().unwrap_or(0.0),
+    )
+}
+
+/// IDF of every query term, from how many of `documents` contain it.
+fn corpus_idf<'a, I>(
+    query_terms: I,
+    documents: &[Vec<String>],
+    total_docs: usize,
+    variant: IdfVariant,
+) -> HashMap<&'a str, f64>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let total_docs_f = total_docs as f64;
+    let mut idf = HashMap::new();
+    for term in query_terms {
+        if idf.contains_key(term.as_str()) {
+            continue;
+        }
+        let df = documents
+            .iter()
+            .filter(|doc| doc.iter().any(|t| t == term))
+            .count();
+        idf.insert(term.as_str(), variant.idf(total_docs_f, df as f64));
+    }
+    idf
+}
+
+/// Number of `documents` containing each distinct term.
+fn count_doc_freqs(documents: &[Vec<String>]) -> HashMap<&str, usize> {
+    let mut doc_freqs: HashMap<&str, usize> = HashMap::new();
+    for doc in documents {
+        let distinct: HashSet<&str> = doc.iter().map(String::as_str).collect();
+        for term in distinct {
+            *doc_freqs.entry(term).or_insert(0) += 1;
+        }
+    }
+    doc_freqs
+}
+
+/// Shared BM25 loop; `delta` is added to the TF component of present terms (BM25+).
+/// `idf(i, term)` weighs the `i`-th query term, which lets callers fold in query weights.
+fn score_documents<F>(
+    query_terms: &[String],
+    documents: &[Vec<String>],
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    delta: f64,
+    idf: F,
+) -> Vec<f64>
+where
+    F: Fn(usize, &str) -> f64 + Sync,
+{
+    let term_freqs = doc_term_freqs(documents);
+    score_term_freqs(
+        query_terms,
+        documents,
+        &term_freqs,
+        avg_doc_len,
+        k1,
+        b,
+        delta,
+        idf,
+    )
+}
+
+/// Term frequencies of every document, built once so several queries can share them.
+fn doc_term_freqs(documents: &[Vec<String>]) -> Vec<HashMap<&str, usize>> {
+    map_range(documents.len(), |i| term_freqs_of(&documents[i]))
+}
+
+/// Term frequencies of one document.
+fn term_freqs_of(doc: &[String]) -> HashMap<&str, usize> {
+    let mut term_freq: HashMap<&str, usize> = HashMap::new();
+    for t in doc {
+        *term_freq.entry(t.as_str()).or_insert(0) += 1;
+    }
+    term_freq
+}
+
+/// `score_documents` over term frequencies from `doc_term_freqs`.
+#[allow(clippy::too_many_arguments)]
+fn score_term_freqs<F>(
+    query_terms: &[String],
+    documents: &[Vec<String>],
+    term_freqs: &[HashMap<&str, usize>],
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    delta: f64,
+    idf: F,
+) -> Vec<f64>
+where
+    F: Fn(usize, &str) -> f64 + Sync,
+{
+    let avg_doc_len = if avg_doc_len == 0.0 { 1.0 } else { avg_doc_len };
+
+    map_range(documents.len(), |d| {
+        doc_score(
+            query_terms,
+            &documents[d],
+            &term_freqs[d],
+            avg_doc_len,
+            k1,
+            b,
+            delta,
+            &idf,
+        )
+    })
+}
+
+/// BM25 score of one document; `avg_doc_len` must already be non-zero.
+#[allow(clippy::too_many_arguments)]
+fn doc_score<F>(
+    query_terms: &[String],
+    doc: &[String],
+    term_freq: &HashMap<&str, usize>,
+    avg_doc_len: f64,
+    k1: f64,
+    b: f64,
+    delta: f64,
+    idf: F,
+) -> f64
+where
+    F: Fn(usize, &str) -> f64,
+{
+    if doc.is_empty() {
+        return 0.0;
+    }
+
+    let doc_len = doc.len() as f64;
+    let mut score = 0.0_f64;
+
+    for (i, term) in query_terms.iter().enumerate() {
+        let tf = match term_freq.get(term.as_str()) {
+            Some(&f) => f as f64,
+            None => continue,
+        };
+
+        // TF with saturation and length normalization
+        let tf_component = (tf * (k1 + 1.0)) / (tf + k1 * (1.0 - b + b * doc_len / avg_doc_len));
+
+        score += idf(i, term) * (tf_component + delta);
+    }
+
+    score
 }
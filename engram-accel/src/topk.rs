@@ -0,0 +1,78 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// A scored candidate ordered so that "greater" means better ranked:
+/// higher score first, ties broken by the smaller id.
+#[derive(Clone, Copy)]
+struct Entry<I> {
+    id: I,
+    score: f64,
+}
+
+impl<I: Ord> Ord for Entry<I> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl<I: Ord> PartialOrd for Entry<I> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<I: Ord> PartialEq for Entry<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<I: Ord> Eq for Entry<I> {}
+
+/// Bounded min-heap keeping the best `k` candidates seen so far.
+pub(crate) struct TopK<I> {
+    k: usize,
+    heap: BinaryHeap<Reverse<Entry<I>>>,
+}
+
+impl<I: Ord + Copy> TopK<I> {
+    pub(crate) fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k.min(1024)),
+        }
+    }
+
+    pub(crate) fn push(&mut self, id: I, score: f64) {
+        if self.k == 0 {
+            return;
+        }
+        let entry = Entry { id, score };
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse(entry));
+        } else if let Some(mut worst) = self.heap.peek_mut() {
+            if entry > worst.0 {
+                *worst = Reverse(entry);
+            }
+        }
+    }
+
+    /// Fold another collector's candidates into this one (used to combine rayon partials).
+    pub(crate) fn merge(mut self, other: Self) -> Self {
+        for Reverse(entry) in other.heap {
+            self.push(entry.id, entry.score);
+        }
+        self
+    }
+
+    /// Drain into `(id, score)` pairs sorted best first.
+    pub(crate) fn into_sorted_vec(self) -> Vec<(I, f64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(entry)| (entry.id, entry.score))
+            .collect()
+    }
+}
@@ -1,9 +1,1292 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use numpy::{PyArray1, PyReadonlyArray1, PyReadonlyArray2};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::PyType;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::topk::TopK;
 
 /// Cosine similarity between two vectors.
+///
+/// Empty, mismatched-length or zero-norm input scores 0.0, the same as truly
+/// orthogonal vectors. With `strict=True` those cases raise a ValueError
+/// describing the problem instead.
+#[pyfunction]
+#[pyo3(signature = (a, b, strict=false))]
+pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>, strict: bool) -> PyResult<f64> {
+    if strict {
+        check_cosine_input(&a, &b)?;
+    }
+    Ok(cosine_pair(&a, &b))
+}
+
+/// Compute cosine similarity of one query vector against N stored vectors.
+/// The loop runs in Rust with rayon parallelism for large batches, with the
+/// GIL released so other Python threads keep running meanwhile.
+///
+/// `parallel_threshold` is the batch size at which rayon kicks in (default 256);
+/// `0` always runs in parallel and a very large value forces serial.
+///
+/// `nan_policy` controls NaN components: `"zero"` (default) scores the whole row
+/// 0.0, `"skip"` treats NaN components as 0.0, and `"error"` raises a ValueError
+/// naming the first offending row.
+///
+/// With `clamp=True` every score is clamped to [-1.0, 1.0], absorbing rounding
+/// overshoot such as 1.0000002 for near-identical vectors.
+#[pyfunction]
+#[pyo3(signature = (query, store, parallel_threshold=None, nan_policy="zero", clamp=false))]
+pub fn cosine_similarity_batch(
+    py: Python<'_>,
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    parallel_threshold: Option<usize>,
+    nan_policy: &str,
+    clamp: bool,
+) -> PyResult<Vec<f64>> {
+    let policy = NanPolicy::parse(nan_policy)?;
+    let threshold = parallel_threshold.unwrap_or(PARALLEL_THRESHOLD);
+    py.allow_threads(move || cosine_batch(&query, &store, policy, threshold, clamp))
+}
+
+/// Streaming `cosine_similarity_batch` for stores too large to pass at once.
+///
+/// `store` may be any iterable of vectors (e.g. a generator reading from disk);
+/// rows are pulled `chunk_size` at a time, scored with the GIL released, and
+/// handed to `callback(start_index, scores)` before the next chunk is read, so
+/// only one chunk of rows and scores is alive at a time. Exceptions from the
+/// iterable or the callback propagate. Returns the number of rows scored.
+#[pyfunction]
+pub fn cosine_similarity_chunked(
+    py: Python<'_>,
+    query: Vec<f64>,
+    store: &Bound<'_, PyAny>,
+    chunk_size: usize,
+    callback: &Bound<'_, PyAny>,
+) -> PyResult<usize> {
+    if chunk_size == 0 {
+        return Err(PyValueError::new_err("chunk_size must be positive"));
+    }
+
+    let query_norm = l2_norm(&query, false);
+    let mut rows = store.iter()?;
+    let mut start = 0;
+    loop {
+        let mut chunk: Vec<Vec<f64>> = Vec::with_capacity(chunk_size);
+        for row in rows.by_ref().take(chunk_size) {
+            chunk.push(row?.extract()?);
+        }
+        if chunk.is_empty() {
+            return Ok(start);
+        }
+
+        let scores = py.allow_threads(|| {
+            map_rows(&chunk, |vec| {
+                cosine_sim_with_prenorm(&query, query_norm, vec)
+            })
+        });
+        let len = chunk.len();
+        drop(chunk);
+        callback.call1((start, scores))?;
+        start += len;
+    }
+}
+
+/// `cosine_similarity_batch` that also reports which store rows have the wrong
+/// dimension. Those rows still score 0.0; their indices come back in ascending
+/// order so ingest bugs surface without failing the whole request.
+#[pyfunction]
+pub fn cosine_similarity_batch_checked(
+    py: Python<'_>,
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+) -> PyResult<(Vec<f64>, Vec<usize>)> {
+    py.allow_threads(move || {
+        let scores = cosine_batch(&query, &store, NanPolicy::Zero, PARALLEL_THRESHOLD, false)?;
+        let mismatched = store
+            .iter()
+            .enumerate()
+            .filter(|(_, vec)| vec.len() != query.len())
+            .map(|(i, _)| i)
+            .collect();
+        Ok((scores, mismatched))
+    })
+}
+
+/// Cosine similarity of the query against only the `candidate_indices` rows
+/// of `store`, returned aligned to `candidate_indices`. Raises IndexError if
+/// an index is out of range.
+#[pyfunction]
+pub fn cosine_similarity_subset(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    candidate_indices: Vec<usize>,
+) -> PyResult<Vec<f64>> {
+    if let Some(&i) = candidate_indices.iter().find(|&&i| i >= store.len()) {
+        return Err(PyIndexError::new_err(format!(
+            "candidate index {i} out of range for store of {} rows",
+            store.len()
+        )));
+    }
+    let query_norm = l2_norm(&query, false);
+    Ok(map_rows(&candidate_indices, |&i| {
+        cosine_sim_with_prenorm(&query, query_norm, &store[i])
+    }))
+}
+
+/// Indices of vectors whose length differs from `expected_dim`, in ascending
+/// order. An empty result means every vector is well-formed.
+#[pyfunction]
+pub fn validate_dimensions(vectors: Vec<Vec<f64>>, expected_dim: usize) -> Vec<usize> {
+    vectors
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| v.len() != expected_dim)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Cosine similarity between two f32 vectors, accumulated in f64.
+#[pyfunction]
+pub fn cosine_similarity_f32(a: Vec<f32>, b: Vec<f32>) -> f32 {
+    if a.is_empty() || b.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    let norm_a = l2_norm(&a, false);
+    cosine_checked_with_prenorm(&a, norm_a, &b).unwrap_or(0.0) as f32
+}
+
+/// f32 counterpart of `cosine_similarity_batch`.
+/// Takes and returns f32 to halve memory traffic; accumulation is still f64.
+#[pyfunction]
+#[pyo3(signature = (query, store, nan_policy="zero", clamp=false))]
+pub fn cosine_similarity_batch_f32(
+    query: Vec<f32>,
+    store: Vec<Vec<f32>>,
+    nan_policy: &str,
+    clamp: bool,
+) -> PyResult<Vec<f32>> {
+    let policy = NanPolicy::parse(nan_policy)?;
+    let scores = cosine_batch(&query, &store, policy, PARALLEL_THRESHOLD, clamp)?;
+    Ok(scores.into_iter().map(|s| s as f32).collect())
+}
+
+/// numpy counterpart of `cosine_similarity_batch` for an (N, D) store matrix.
+///
+/// Contiguous rows are read in place from the array buffer instead of being
+/// copied into per-row Vecs; strided (non-contiguous) rows are copied once.
+#[pyfunction]
+#[pyo3(signature = (query, store, nan_policy="zero", clamp=false))]
+pub fn cosine_similarity_batch_np<'py>(
+    py: Python<'py>,
+    query: PyReadonlyArray1<'py, f64>,
+    store: PyReadonlyArray2<'py, f64>,
+    nan_policy: &str,
+    clamp: bool,
+) -> PyResult<Py<PyArray1<f64>>> {
+    let policy = NanPolicy::parse(nan_policy)?;
+    let query: Vec<f64> = query.as_array().iter().copied().collect();
+    let store = store.as_array();
+    let rows: Vec<Cow<[f64]>> = store
+        .rows()
+        .into_iter()
+        .map(|row| match row.to_slice() {
+            Some(slice) => Cow::Borrowed(slice),
+            None => Cow::Owned(row.to_vec()),
+        })
+        .collect();
+
+    let scores = cosine_batch(&query, &rows, policy, PARALLEL_THRESHOLD, clamp)?;
+    Ok(PyArray1::from_vec_bound(py, scores).unbind())
+}
+
+/// Cosine distance (`1 - cosine`) of one query vector against N stored vectors.
+/// Zero-norm or mismatched-dimension rows score 1.0 so they never look like matches.
+#[pyfunction]
+pub fn cosine_distance_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    let query_norm = query.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if query.is_empty() || query_norm == 0.0 {
+        return vec![1.0; store.len()];
+    }
+
+    map_rows(&store, |vec| {
+        cosine_checked_with_prenorm(&query, query_norm, vec).map_or(1.0, |sim| 1.0 - sim)
+    })
+}
+
+/// Angular distance `acos(cosine) / pi` of one query against N stored vectors.
+///
+/// Unlike raw cosine this is a proper metric, with values in [0, 1]. The cosine
+/// is clamped to [-1, 1] first so rounding never leaves `acos`'s domain.
+/// Zero-norm or mismatched-dimension rows score 1.0.
+#[pyfunction]
+pub fn angular_distance_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    let query_norm = l2_norm(&query, false);
+    map_rows(&store, |vec| {
+        cosine_checked_with_prenorm(&query, query_norm, vec).map_or(1.0, |sim| {
+            sim.clamp(-1.0, 1.0).acos() / std::f64::consts::PI
+        })
+    })
+}
+
+/// Cosine similarity with a per-dimension weight applied to every component.
+///
+/// The dot product uses `weights[i]` as given while the norms use `|weights[i]|`,
+/// so negative weights flip a dimension's contribution but scores stay in [-1, 1].
+#[pyfunction]
+pub fn weighted_cosine_batch(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    weights: Vec<f64>,
+) -> PyResult<Vec<f64>> {
+    if weights.len() != query.len() {
+        return Err(PyValueError::new_err(format!(
+            "weights has length {} but query has dimension {}",
+            weights.len(),
+            query.len()
+        )));
+    }
+
+    let query_norm = query
+        .iter()
+        .zip(weights.iter())
+        .map(|(x, w)| w.abs() * x * x)
+        .sum::<f64>()
+        .sqrt();
+    if query.is_empty() || query_norm == 0.0 {
+        return Ok(vec![0.0; store.len()]);
+    }
+
+    Ok(map_rows(&store, |vec| {
+        if vec.len() != query.len() {
+            return 0.0;
+        }
+        let mut dot = 0.0_f64;
+        let mut norm_b = 0.0_f64;
+        for ((x, y), w) in query.iter().zip(vec.iter()).zip(weights.iter()) {
+            dot += w * x * y;
+            norm_b += w.abs() * y * y;
+        }
+        finish_cosine(dot, query_norm, norm_b).unwrap_or(0.0)
+    }))
+}
+
+/// Cosine similarity over only the dimensions where `mask` is true.
+///
+/// Masked-out dimensions are left out of both the dot product and the norms,
+/// as if the vectors had been sliced first. Raises ValueError if `mask` does not
+/// match the query dimension; mismatched rows and rows whose kept dimensions
+/// are all zero score 0.0.
+#[pyfunction]
+pub fn masked_cosine_batch(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    mask: Vec<bool>,
+) -> PyResult<Vec<f64>> {
+    if mask.len() != query.len() {
+        return Err(PyValueError::new_err(format!(
+            "mask has length {} but query has dimension {}",
+            mask.len(),
+            query.len()
+        )));
+    }
+
+    let query_norm = query
+        .iter()
+        .zip(mask.iter())
+        .filter(|(_, &keep)| keep)
+        .map(|(x, _)| x * x)
+        .sum::<f64>()
+        .sqrt();
+    if query_norm == 0.0 {
+        return Ok(vec![0.0; store.len()]);
+    }
+
+    Ok(map_rows(&store, |vec| {
+        if vec.len() != query.len() {
+            return 0.0;
+        }
+        let mut dot = 0.0_f64;
+        let mut norm_b = 0.0_f64;
+        for ((x, y), _) in query
+            .iter()
+            .zip(vec.iter())
+            .zip(mask.iter())
+            .filter(|(_, &keep)| keep)
+        {
+            dot += x * y;
+            norm_b += y * y;
+        }
+        finish_cosine(dot, query_norm, norm_b).unwrap_or(0.0)
+    }))
+}
+
+/// L2-normalize every vector in place and return them.
+/// Zero-norm and empty vectors are passed through unchanged rather than becoming NaN.
+#[pyfunction]
+pub fn normalize_batch(mut vectors: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let normalize = |vec: &mut Vec<f64>| {
+        let norm = l2_norm(vec, false);
+        if norm == 0.0 || !norm.is_finite() {
+            return;
+        }
+        for x in vec.iter_mut() {
+            *x /= norm;
+        }
+    };
+
+    if vectors.len() < PARALLEL_THRESHOLD {
+        vectors.iter_mut().for_each(normalize);
+    } else {
+        vectors.par_iter_mut().for_each(normalize);
+    }
+    vectors
+}
+
+/// Whether each vector's L2 norm is within `tolerance` of 1.0.
+/// NaN components make a vector report false.
+#[pyfunction]
+pub fn is_normalized_batch(vectors: Vec<Vec<f64>>, tolerance: f64) -> Vec<bool> {
+    map_rows(&vectors, |vec| {
+        (l2_norm(vec, false) - 1.0).abs() <= tolerance
+    })
+}
+
+/// Element-wise mean of a set of vectors (mean pooling).
+///
+/// Empty rows are skipped; the remaining rows must share one dimension or a
+/// ValueError is raised. Returns an empty vector when there is nothing to average.
+#[pyfunction]
+pub fn centroid(vectors: Vec<Vec<f64>>) -> PyResult<Vec<f64>> {
+    let mut rows = vectors.iter().enumerate().filter(|(_, v)| !v.is_empty());
+    let Some((_, first)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+
+    let mut sum = first.clone();
+    let mut count = 1usize;
+    for (i, vec) in rows {
+        if vec.len() != sum.len() {
+            return Err(PyValueError::new_err(format!(
+                "row {i} has dimension {} but expected {}",
+                vec.len(),
+                sum.len()
+            )));
+        }
+        for (acc, x) in sum.iter_mut().zip(vec.iter()) {
+            *acc += x;
+        }
+        count += 1;
+    }
+
+    let count = count as f64;
+    for acc in sum.iter_mut() {
+        *acc /= count;
+    }
+    Ok(sum)
+}
+
+/// Cosine similarity for sparse vectors given as (dimension, value) pairs.
+///
+/// Each vector must be sorted by dimension; the dot product is a merge over the
+/// two index lists. Empty vectors score 0.0.
+#[pyfunction]
+pub fn sparse_cosine_batch(query: Vec<(u32, f64)>, store: Vec<Vec<(u32, f64)>>) -> Vec<f64> {
+    debug_assert!(is_sorted_sparse(&query), "sparse query must be sorted");
+    let query_norm = query.iter().map(|&(_, x)| x * x).sum::<f64>().sqrt();
+    if query.is_empty() || query_norm == 0.0 {
+        return vec![0.0; store.len()];
+    }
+
+    map_rows(&store, |vec| {
+        debug_assert!(is_sorted_sparse(vec), "sparse store rows must be sorted");
+        if vec.is_empty() {
+            return 0.0;
+        }
+        let mut dot = 0.0_f64;
+        let (mut i, mut j) = (0, 0);
+        while i < query.len() && j < vec.len() {
+            let (qi, qv) = query[i];
+            let (vi, vv) = vec[j];
+            match qi.cmp(&vi) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    dot += qv * vv;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let norm_b = vec.iter().map(|&(_, y)| y * y).sum::<f64>();
+        finish_cosine(dot, query_norm, norm_b).unwrap_or(0.0)
+    })
+}
+
+/// Jaccard similarity `|A ∩ B| / |A ∪ B|` between sets of element IDs.
+///
+/// Each set must be a sorted list of unique IDs; the intersection is a merge over
+/// the two lists. Two empty sets score 1.0, empty against non-empty scores 0.0.
+/// Raises ValueError if the query or any store set is unsorted or has duplicates.
+#[pyfunction]
+pub fn jaccard_similarity_batch(query: Vec<u32>, store: Vec<Vec<u32>>) -> PyResult<Vec<f64>> {
+    if !is_strictly_sorted(&query) {
+        return Err(PyValueError::new_err(
+            "query must be a sorted list of unique IDs",
+        ));
+    }
+    if let Some(i) = store.iter().position(|set| !is_strictly_sorted(set)) {
+        return Err(PyValueError::new_err(format!(
+            "store set {i} must be a sorted list of unique IDs"
+        )));
+    }
+
+    Ok(map_rows(&store, |set| {
+        if query.is_empty() && set.is_empty() {
+            return 1.0;
+        }
+        let mut intersection = 0usize;
+        let (mut i, mut j) = (0, 0);
+        while i < query.len() && j < set.len() {
+            match query[i].cmp(&set[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    intersection += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        let union = query.len() + set.len() - intersection;
+        intersection as f64 / union as f64
+    }))
+}
+
+/// Whether `ids` is ascending with no repeats.
+fn is_strictly_sorted(ids: &[u32]) -> bool {
+    ids.windows(2).all(|w| w[0] < w[1])
+}
+
+/// Hamming distance between bit-packed binary embeddings (u64 words).
+/// Any store row whose word count differs from the query raises a ValueError.
+#[pyfunction]
+pub fn hamming_distance_batch(query: Vec<u64>, store: Vec<Vec<u64>>) -> PyResult<Vec<u32>> {
+    if let Some(i) = store.iter().position(|words| words.len() != query.len()) {
+        return Err(PyValueError::new_err(format!(
+            "store row {i} has {} words but query has {}",
+            store[i].len(),
+            query.len()
+        )));
+    }
+
+    Ok(map_rows(&store, |words| {
+        query
+            .iter()
+            .zip(words.iter())
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum()
+    }))
+}
+
+/// Quantize f32 vectors to symmetric int8 codes with one scale per vector.
+///
+/// Each vector is mapped to `round(x / scale)` with `scale = max|x| / 127`, so
+/// `code * scale` approximates the original. All-zero vectors get scale 0.0.
+#[pyfunction]
+pub fn quantize_vectors(vectors: Vec<Vec<f32>>) -> (Vec<Vec<i8>>, Vec<f32>) {
+    map_rows(&vectors, |vec| {
+        let max_abs = vec.iter().fold(0.0_f32, |m, x| m.max(x.abs()));
+        if max_abs == 0.0 || !max_abs.is_finite() {
+            return (vec![0; vec.len()], 0.0);
+        }
+        let scale = max_abs / 127.0;
+        let codes = vec
+            .iter()
+            .map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8)
+            .collect();
+        (codes, scale)
+    })
+    .into_iter()
+    .unzip()
+}
+
+/// Cosine similarity over int8 codes produced by `quantize_vectors`.
+///
+/// Dot products and norms accumulate in i64 and are rescaled by the per-vector
+/// scales. Rows with a zero scale or mismatched dimension score 0.0.
+#[pyfunction]
+pub fn cosine_similarity_batch_i8(
+    query: Vec<i8>,
+    query_scale: f32,
+    store: Vec<Vec<i8>>,
+    scales: Vec<f32>,
+) -> PyResult<Vec<f32>> {
+    if scales.len() != store.len() {
+        return Err(PyValueError::new_err(format!(
+            "scales has length {} but store has {} rows",
+            scales.len(),
+            store.len()
+        )));
+    }
+
+    let query_scale = query_scale as f64;
+    let query_norm_sq: i64 = query.iter().map(|&x| x as i64 * x as i64).sum();
+    let query_norm = query_scale * (query_norm_sq as f64).sqrt();
+    if query.is_empty() || query_norm == 0.0 {
+        return Ok(vec![0.0; store.len()]);
+    }
+
+    let rows: Vec<(&Vec<i8>, f32)> = store.iter().zip(scales.iter().copied()).collect();
+    Ok(map_rows(&rows, |&(vec, scale)| {
+        if vec.len() != query.len() {
+            return 0.0;
+        }
+        let mut dot = 0_i64;
+        let mut norm_b = 0_i64;
+        for (&x, &y) in query.iter().zip(vec.iter()) {
+            dot += x as i64 * y as i64;
+            norm_b += y as i64 * y as i64;
+        }
+        let scale = scale as f64;
+        let dot = dot as f64 * query_scale * scale;
+        let norm_b_sq = norm_b as f64 * scale * scale;
+        finish_cosine(dot, query_norm, norm_b_sq).unwrap_or(0.0) as f32
+    }))
+}
+
+/// All-pairs cosine similarity within one set of vectors.
+///
+/// Returns the symmetric NxN matrix with the diagonal set to 1.0. Only the upper
+/// triangle is computed (in parallel over rows for large inputs) and then mirrored.
+#[pyfunction]
+pub fn pairwise_cosine_matrix(vectors: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let n = vectors.len();
+    let norms = map_rows(&vectors, |vec| l2_norm(vec, false));
+
+    // upper[i][j - i - 1] holds the similarity of vectors i and j for j > i.
+    let upper = map_range(n, |i| {
+        ((i + 1)..n)
+            .map(|j| cosine_with_norms(&vectors[i], norms[i], &vectors[j], norms[j]))
+            .collect::<Vec<f64>>()
+    });
+
+    (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| match i.cmp(&j) {
+                    Ordering::Equal => 1.0,
+                    Ordering::Less => upper[i][j - i - 1],
+                    Ordering::Greater => upper[j][i - j - 1],
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Diversity of a result set: `1 - mean pairwise cosine` over all unique pairs.
+///
+/// Higher means more spread out (identical vectors give 0.0). Sets with fewer
+/// than two vectors return 0.0. Zero-norm or mismatched pairs count as cosine 0.0.
+#[pyfunction]
+pub fn set_diversity(vectors: Vec<Vec<f64>>) -> f64 {
+    let n = vectors.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let norms = map_rows(&vectors, |vec| l2_norm(vec, false));
+
+    let total: f64 = map_range(n, |i| {
+        ((i + 1)..n)
+            .map(|j| cosine_with_norms(&vectors[i], norms[i], &vectors[j], norms[j]))
+            .sum::<f64>()
+    })
+    .into_iter()
+    .sum();
+    let pairs = (n * (n - 1) / 2) as f64;
+    1.0 - total / pairs
+}
+
+/// Cosine similarity of several queries against the same store.
+///
+/// Returns a (num_queries x num_store) matrix. Store norms are computed once and
+/// shared by every query; queries are scored in parallel for large workloads.
+#[pyfunction]
+pub fn cosine_similarity_matrix(queries: Vec<Vec<f64>>, store: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    let store_norms = map_rows(&store, |vec| l2_norm(vec, false));
+
+    // Each query scores the whole store, so go parallel once queries x rows is large.
+    let threshold = PARALLEL_THRESHOLD.div_ceil(store.len().max(1));
+    map_rows_with_threshold(&queries, threshold, |query| {
+        let query_norm = l2_norm(query, false);
+        store
+            .iter()
+            .zip(store_norms.iter())
+            .map(|(vec, &norm)| cosine_with_norms(query, query_norm, vec, norm))
+            .collect()
+    })
+}
+
+/// Cosine matches at or above `threshold`, as (index, score) pairs in index order.
+/// Rows below the cutoff are dropped in Rust, so no dense score vector is built.
+#[pyfunction]
+pub fn cosine_above_threshold(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    threshold: f64,
+) -> Vec<(usize, f64)> {
+    let query_norm = l2_norm(&query, false);
+    filter_map_rows(&store, |i, vec| {
+        let score = cosine_sim_with_prenorm(&query, query_norm, vec);
+        (score >= threshold).then_some((i, score))
+    })
+}
+
+/// Pearson correlation of one query vector against N stored vectors.
+///
+/// Both sides are mean-centered before the cosine-style ratio, giving values in
+/// [-1, 1]. Constant (zero-variance) or mismatched-dimension rows score 0.0.
+#[pyfunction]
+pub fn pearson_correlation_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    let centered_query = mean_centered(&query);
+    let query_norm = l2_norm(&centered_query, false);
+    map_rows(&store, |vec| {
+        pearson_with_centered(&centered_query, query_norm, vec)
+    })
+}
+
+/// Spearman rank correlation of one query vector against N stored vectors.
+///
+/// Each vector is replaced by its ranks (tied values share their average rank)
+/// and Pearson correlation is taken on the ranks. Constant or mismatched-dimension
+/// rows score 0.0.
+#[pyfunction]
+pub fn spearman_correlation_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    let centered_query = mean_centered(&average_ranks(&query));
+    let query_norm = l2_norm(&centered_query, false);
+    map_rows(&store, |vec| {
+        if vec.len() != query.len() {
+            return 0.0;
+        }
+        pearson_with_centered(&centered_query, query_norm, &average_ranks(vec))
+    })
+}
+
+/// Index pairs `(i, j)` with `i < j` whose cosine similarity meets `threshold`.
+///
+/// Only the upper triangle is examined, so there are no self or mirrored pairs.
+/// Pairs come back ordered by `i` then `j`; outer rows run in parallel for large inputs.
+#[pyfunction]
+pub fn find_duplicates(vectors: Vec<Vec<f64>>, threshold: f64) -> Vec<(usize, usize)> {
+    let n = vectors.len();
+    let norms = map_rows(&vectors, |vec| l2_norm(vec, false));
+
+    map_range(n, |i| {
+        ((i + 1)..n)
+            .filter(|&j| {
+                cosine_with_norms(&vectors[i], norms[i], &vectors[j], norms[j]) >= threshold
+            })
+            .map(|j| (i, j))
+            .collect::<Vec<_>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Euclidean (L2) distance of one query vector against N stored vectors.
+/// Rows whose dimension differs from the query score `f64::INFINITY` so they sort last.
+#[pyfunction]
+pub fn euclidean_distance_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    map_rows(&store, |vec| squared_l2_distance(&query, vec).sqrt())
+}
+
+/// Squared Euclidean distance of one query vector against N stored vectors.
+/// Skips the final sqrt, which is wasted work when only ranking by distance.
+/// Rows whose dimension differs from the query score `f64::INFINITY`.
+#[pyfunction]
+pub fn squared_euclidean_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    map_rows(&store, |vec| squared_l2_distance(&query, vec))
+}
+
+/// Manhattan (L1) distance of one query vector against N stored vectors.
+/// Rows whose dimension differs from the query score `f64::INFINITY`.
+#[pyfunction]
+pub fn manhattan_distance_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    map_rows(&store, |vec| l1_distance(&query, vec))
+}
+
+/// Minkowski distance `(sum |x - y|^p)^(1/p)` of one query against N stored vectors.
+///
+/// `p == 1` and `p == 2` take the dedicated L1/L2 paths and `p == inf` gives the
+/// Chebyshev (max) distance. Mismatched rows score `f64::INFINITY`.
+#[pyfunction]
+pub fn minkowski_distance_batch(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    p: f64,
+) -> PyResult<Vec<f64>> {
+    if p.is_nan() || p <= 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "p must be positive, got {p}"
+        )));
+    }
+
+    Ok(map_rows(&store, |vec| {
+        if p == 1.0 {
+            return l1_distance(&query, vec);
+        }
+        if p == 2.0 {
+            return squared_l2_distance(&query, vec).sqrt();
+        }
+        if vec.len() != query.len() {
+            return f64::INFINITY;
+        }
+        let diffs = query.iter().zip(vec.iter()).map(|(x, y)| (x - y).abs());
+        if p.is_infinite() {
+            diffs.fold(0.0, f64::max)
+        } else {
+            diffs.map(|d| d.powf(p)).sum::<f64>().powf(1.0 / p)
+        }
+    }))
+}
+
+/// Kullback-Leibler divergence `KL(query || store[i]) = sum(q * ln(q / p))`.
+///
+/// KL is asymmetric: `query` is the reference distribution `q` and each store
+/// row is the approximating distribution `p`. `epsilon` is added to every
+/// component of both sides so zeros never hit `ln(0)` or divide by zero. Inputs
+/// are used as given, not renormalized. Negative components raise a ValueError;
+/// mismatched-dimension rows score `f64::INFINITY`.
+#[pyfunction]
+pub fn kl_divergence_batch(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    epsilon: f64,
+) -> PyResult<Vec<f64>> {
+    if epsilon.is_nan() || epsilon < 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "epsilon must be non-negative, got {epsilon}"
+        )));
+    }
+    check_non_negative("query", &query)?;
+    for (i, vec) in store.iter().enumerate() {
+        check_non_negative(&format!("store row {i}"), vec)?;
+    }
+
+    Ok(map_rows(&store, |vec| {
+        if vec.len() != query.len() {
+            return f64::INFINITY;
+        }
+        query
+            .iter()
+            .zip(vec.iter())
+            .map(|(q, p)| {
+                let (q, p) = (q + epsilon, p + epsilon);
+                if q == 0.0 {
+                    0.0
+                } else {
+                    q * (q / p).ln()
+                }
+            })
+            .sum()
+    }))
+}
+
+/// Jensen-Shannon distance (square root of the JS divergence, log base 2).
+///
+/// Symmetric and a true metric in [0, 1]. Each vector is scaled to sum to 1
+/// first; zero components are handled through the mixture midpoint, so no
+/// smoothing is needed. Negative components raise a ValueError; all-zero or
+/// mismatched-dimension rows score 1.0.
+#[pyfunction]
+pub fn jensen_shannon_distance_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> PyResult<Vec<f64>> {
+    check_non_negative("query", &query)?;
+    for (i, vec) in store.iter().enumerate() {
+        check_non_negative(&format!("store row {i}"), vec)?;
+    }
+
+    let query_sum: f64 = query.iter().sum();
+    Ok(map_rows(&store, |vec| {
+        let vec_sum: f64 = vec.iter().sum();
+        if vec.len() != query.len() || query_sum == 0.0 || vec_sum == 0.0 {
+            return 1.0;
+        }
+        let divergence: f64 = query
+            .iter()
+            .zip(vec.iter())
+            .map(|(q, p)| {
+                let (q, p) = (q / query_sum, p / vec_sum);
+                let m = 0.5 * (q + p);
+                let term = |x: f64| if x == 0.0 { 0.0 } else { x * (x / m).log2() };
+                0.5 * (term(q) + term(p))
+            })
+            .sum();
+        divergence.clamp(0.0, 1.0).sqrt()
+    }))
+}
+
+/// Raw inner product of one query vector against N stored vectors.
+/// No normalization is applied, so vector magnitude contributes to the score.
+#[pyfunction]
+pub fn dot_product_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
+    map_rows(&store, |vec| {
+        if vec.len() != query.len() {
+            return 0.0;
+        }
+        query.iter().zip(vec.iter()).map(|(x, y)| x * y).sum()
+    })
+}
+
+/// Cosine similarity for unit vectors, computed as a bare dot product.
+///
+/// Precondition: the query and every stored vector are already L2-normalized
+/// (see `normalize_batch` / `is_normalized_batch`). No norms are computed, so
+/// un-normalized input silently yields dot products rather than cosines.
+/// Mismatched-dimension rows score 0.0. This is the cheapest similarity path.
+#[pyfunction]
+pub fn dot_similarity_batch_unit(
+    py: Python<'_>,
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+) -> Vec<f64> {
+    py.allow_threads(move || {
+        map_rows(&store, |vec| {
+            if vec.len() != query.len() {
+                return 0.0;
+            }
+            query.iter().zip(vec.iter()).map(|(x, y)| x * y).sum()
+        })
+    })
+}
+
+/// `cosine_similarity_batch` with caller-cached store norms, so only the dot
+/// products are computed. `store_norms[i]` is the L2 norm of `store[i]` and
+/// must have one entry per row or a ValueError is raised. Zero norms and
+/// mismatched-dimension rows score 0.0.
+#[pyfunction]
+pub fn cosine_similarity_batch_prenorm(
+    py: Python<'_>,
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    store_norms: Vec<f64>,
+) -> PyResult<Vec<f64>> {
+    if store_norms.len() != store.len() {
+        return Err(PyValueError::new_err(format!(
+            "store_norms has length {} but store has {} rows",
+            store_norms.len(),
+            store.len()
+        )));
+    }
+
+    let query_norm = l2_norm(&query, false);
+    Ok(py.allow_threads(move || {
+        map_range(store.len(), |i| {
+            cosine_with_norms(&query, query_norm, &store[i], store_norms[i])
+        })
+    }))
+}
+
+/// Top-k cosine matches of a query against N stored vectors.
+///
+/// Only k candidates are kept in a bounded heap instead of sorting every score.
+/// Returns (index, score) pairs sorted by descending score, ties broken by smaller index.
+///
+/// With `min_score`, rows scoring below it never enter the heap, so fewer than
+/// k pairs come back when not enough rows clear the cutoff.
+#[pyfunction]
+#[pyo3(signature = (query, store, k, min_score=None))]
+pub fn cosine_topk(
+    query: Vec<f64>,
+    store: Vec<Vec<f64>>,
+    k: usize,
+    min_score: Option<f64>,
+) -> Vec<(usize, f64)> {
+    if store.is_empty() || k == 0 {
+        return Vec::new();
+    }
+
+    let query_norm = query.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if query_norm == 0.0 {
+        if min_score.is_some_and(|min| min > 0.0) {
+            return Vec::new();
+        }
+        // Every row scores 0.0, so the best k are simply the first k.
+        return (0..k.min(store.len())).map(|i| (i, 0.0)).collect();
+    }
+
+    topk_rows(&store, k, min_score, |vec| {
+        cosine_sim_with_prenorm(&query, query_norm, vec)
+    })
+}
+
+/// Index and cosine score of the single best match for `query`, in one pass
+/// with no score vector allocated. Ties go to the smaller index. Raises
+/// ValueError for an empty store, which has no best match.
 #[pyfunction]
-pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>) -> f64 {
+pub fn cosine_argmax(query: Vec<f64>, store: Vec<Vec<f64>>) -> PyResult<(usize, f64)> {
+    if store.is_empty() {
+        return Err(PyValueError::new_err("store is empty"));
+    }
+
+    let query_norm = l2_norm(&query, false);
+    if query_norm == 0.0 {
+        return Ok((0, 0.0));
+    }
+
+    let score = |i: usize| (i, cosine_sim_with_prenorm(&query, query_norm, &store[i]));
+    let better =
+        |a: (usize, f64), b: (usize, f64)| match b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)) {
+            Ordering::Greater => b,
+            _ => a,
+        };
+    Ok(if store.len() < PARALLEL_THRESHOLD {
+        (0..store.len()).map(score).reduce(better)
+    } else {
+        (0..store.len())
+            .into_par_iter()
+            .map(score)
+            .reduce_with(better)
+    }
+    .unwrap_or((0, 0.0)))
+}
+
+/// Maximal Marginal Relevance reranking of candidate vectors.
+///
+/// Greedily picks up to k candidates maximizing
+/// `lambda * relevance - (1 - lambda) * max cosine to already-selected candidates`,
+/// ties broken by smaller index. An empty `relevance` uses each candidate's cosine
+/// to `query_vec`; otherwise it must have one entry per candidate or a ValueError
+/// is raised. Returns the chosen indices in selection order.
+#[pyfunction]
+pub fn mmr_rerank(
+    query_vec: Vec<f64>,
+    candidate_vecs: Vec<Vec<f64>>,
+    relevance: Vec<f64>,
+    lambda: f64,
+    k: usize,
+) -> PyResult<Vec<usize>> {
+    let n = candidate_vecs.len();
+    if !relevance.is_empty() && relevance.len() != n {
+        return Err(PyValueError::new_err(format!(
+            "relevance has length {} but there are {n} candidates",
+            relevance.len()
+        )));
+    }
+    let norms: Vec<f64> = candidate_vecs.iter().map(|v| l2_norm(v, false)).collect();
+    let relevance = if relevance.is_empty() {
+        let query_norm = l2_norm(&query_vec, false);
+        (0..n)
+            .map(|i| cosine_with_norms(&query_vec, query_norm, &candidate_vecs[i], norms[i]))
+            .collect()
+    } else {
+        relevance
+    };
+
+    let mut selected = Vec::with_capacity(k.min(n));
+    let mut picked = vec![false; n];
+    // Highest cosine from each candidate to anything selected so far.
+    let mut max_sim = vec![f64::NEG_INFINITY; n];
+
+    while selected.len() < k.min(n) {
+        let mut best: Option<(usize, f64)> = None;
+        for i in (0..n).filter(|&i| !picked[i]) {
+            let redundancy = if selected.is_empty() { 0.0 } else { max_sim[i] };
+            let score = lambda * relevance[i] - (1.0 - lambda) * redundancy;
+            if best.is_none_or(|(_, b)| score > b) {
+                best = Some((i, score));
+            }
+        }
+        let Some((chosen, _)) = best else { break };
+
+        picked[chosen] = true;
+        selected.push(chosen);
+        for i in (0..n).filter(|&i| !picked[i]) {
+            let sim = cosine_with_norms(
+                &candidate_vecs[chosen],
+                norms[chosen],
+                &candidate_vecs[i],
+                norms[i],
+            );
+            max_sim[i] = max_sim[i].max(sim);
+        }
+    }
+
+    Ok(selected)
+}
+
+/// In-memory embedding store kept resident in Rust, searched by cosine.
+///
+/// Norms are computed once on insert, so a search is one dot product per row.
+/// All vectors share the dimension of the first one added.
+#[pyclass]
+pub struct VectorStore {
+    ids: Vec<u64>,
+    vectors: Vec<Vec<f64>>,
+    norms: Vec<f64>,
+    /// id -> position in `ids` / `vectors` / `norms`
+    slots: HashMap<u64, usize>,
+}
+
+#[pymethods]
+impl VectorStore {
+    #[new]
+    fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            norms: Vec::new(),
+            slots: HashMap::new(),
+        }
+    }
+
+    /// Store `vector` under `id`, replacing any vector already stored there.
+    /// Raises ValueError if its dimension differs from the stored vectors.
+    fn add(&mut self, id: u64, vector: Vec<f64>) -> PyResult<()> {
+        self.check_dim(&vector, "vector")?;
+        let norm = l2_norm(&vector, false);
+        match self.slots.get(&id) {
+            Some(&slot) => {
+                self.vectors[slot] = vector;
+                self.norms[slot] = norm;
+            }
+            None => {
+                self.slots.insert(id, self.ids.len());
+                self.ids.push(id);
+                self.vectors.push(vector);
+                self.norms.push(norm);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove `id` from the store. Returns false if it was not stored.
+    fn remove(&mut self, id: u64) -> bool {
+        let Some(slot) = self.slots.remove(&id) else {
+            return false;
+        };
+        self.ids.swap_remove(slot);
+        self.vectors.swap_remove(slot);
+        self.norms.swap_remove(slot);
+        if let Some(&moved) = self.ids.get(slot) {
+            self.slots.insert(moved, slot);
+        }
+        true
+    }
+
+    /// Top-k cosine matches as (id, score), best first, ties broken by smaller id.
+    /// Raises ValueError if the query dimension differs from the stored vectors.
+    fn search(&self, py: Python<'_>, query: Vec<f64>, k: usize) -> PyResult<Vec<(u64, f64)>> {
+        self.check_dim(&query, "query")?;
+        let query_norm = l2_norm(&query, false);
+        let score = |slot: usize| {
+            cosine_with_norms(&query, query_norm, &self.vectors[slot], self.norms[slot])
+        };
+
+        let top = py.allow_threads(|| {
+            if self.ids.len() < PARALLEL_THRESHOLD {
+                let mut top = TopK::new(k);
+                for slot in 0..self.ids.len() {
+                    top.push(self.ids[slot], score(slot));
+                }
+                top
+            } else {
+                (0..self.ids.len())
+                    .into_par_iter()
+                    .fold(
+                        || TopK::new(k),
+                        |mut top, slot| {
+                            top.push(self.ids[slot], score(slot));
+                            top
+                        },
+                    )
+                    .reduce(|| TopK::new(k), TopK::merge)
+            }
+        });
+        Ok(top.into_sorted_vec())
+    }
+
+    /// Write the ids, vectors and cached norms to `path` as a bincode snapshot.
+    fn save(&self, path: &str) -> PyResult<()> {
+        let snapshot = VectorStoreSnapshot {
+            magic: *SNAPSHOT_MAGIC,
+            version: SNAPSHOT_VERSION,
+            ids: self.ids.clone(),
+            vectors: self.vectors.clone(),
+            norms: self.norms.clone(),
+        };
+        let bytes = bincode::serialize(&snapshot)
+            .map_err(|e| PyValueError::new_err(format!("cannot encode vector store: {e}")))?;
+        std::fs::write(path, bytes)
+            .map_err(|e| PyIOError::new_err(format!("cannot write '{path}': {e}")))
+    }
+
+    /// Read a store written by `save`. Raises IOError if the file cannot be
+    /// read and ValueError if it is corrupt or from another snapshot version.
+    #[classmethod]
+    fn load(_cls: &Bound<'_, PyType>, path: &str) -> PyResult<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| PyIOError::new_err(format!("cannot read '{path}': {e}")))?;
+        let snapshot: VectorStoreSnapshot = bincode::deserialize(&bytes)
+            .map_err(|e| PyValueError::new_err(format!("corrupt vector store '{path}': {e}")))?;
+
+        if &snapshot.magic != SNAPSHOT_MAGIC {
+            return Err(PyValueError::new_err(format!(
+                "'{path}' is not a vector store snapshot"
+            )));
+        }
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(PyValueError::new_err(format!(
+                "'{path}' has snapshot version {}, expected {SNAPSHOT_VERSION}",
+                snapshot.version
+            )));
+        }
+        let n = snapshot.ids.len();
+        if snapshot.vectors.len() != n || snapshot.norms.len() != n {
+            return Err(PyValueError::new_err(format!(
+                "corrupt vector store '{path}': {n} ids, {} vectors, {} norms",
+                snapshot.vectors.len(),
+                snapshot.norms.len()
+            )));
+        }
+
+        let slots: HashMap<u64, usize> = snapshot
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(slot, &id)| (id, slot))
+            .collect();
+        if slots.len() != n {
+            return Err(PyValueError::new_err(format!(
+                "corrupt vector store '{path}': duplicate ids"
+            )));
+        }
+
+        Ok(Self {
+            ids: snapshot.ids,
+            vectors: snapshot.vectors,
+            norms: snapshot.norms,
+            slots,
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn __contains__(&self, id: u64) -> bool {
+        self.slots.contains_key(&id)
+    }
+}
+
+impl VectorStore {
+    fn check_dim(&self, vector: &[f64], what: &str) -> PyResult<()> {
+        match self.vectors.first() {
+            Some(first) if first.len() != vector.len() => Err(PyValueError::new_err(format!(
+                "{what} has dimension {}, store has dimension {}",
+                vector.len(),
+                first.len()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Leading bytes of every `VectorStore` snapshot file.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"ENGRAMVS";
+/// Bumped whenever the snapshot layout changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// On-disk layout of a `VectorStore`; `slots` is rebuilt on load.
+#[derive(Serialize, Deserialize)]
+struct VectorStoreSnapshot {
+    magic: [u8; 8],
+    version: u32,
+    ids: Vec<u64>,
+    vectors: Vec<Vec<f64>>,
+    norms: Vec<f64>,
+}
+
+/// How batch cosine treats NaN components.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NanPolicy {
+    /// NaN poisons the row, which then scores 0.0.
+    Zero,
+    /// NaN components contribute nothing to the dot product or norms.
+    Skip,
+    /// Any NaN raises a ValueError.
+    Error,
+}
+
+impl NanPolicy {
+    fn parse(name: &str) -> PyResult<Self> {
+        match name {
+            "zero" => Ok(NanPolicy::Zero),
+            "skip" => Ok(NanPolicy::Skip),
+            "error" => Ok(NanPolicy::Error),
+            other => Err(PyValueError::new_err(format!(
+                "unknown nan_policy '{other}', expected 'zero', 'skip' or 'error'"
+            ))),
+        }
+    }
+}
+
+/// Cosine of `query` against every row, for composite scorers in other modules.
+/// Zero-norm or mismatched-dimension rows score 0.0.
+pub fn cosine_scores(query: &[f64], store: &[Vec<f64>]) -> Vec<f64> {
+    let query_norm = l2_norm(query, false);
+    map_rows(store, |vec| cosine_sim_with_prenorm(query, query_norm, vec))
+}
+
+/// Shared body of the `cosine_similarity_batch*` variants.
+fn cosine_batch<T, R>(
+    query: &[T],
+    store: &[R],
+    policy: NanPolicy,
+    threshold: usize,
+    clamp: bool,
+) -> PyResult<Vec<f64>>
+where
+    T: Component + Sync,
+    R: AsRef<[T]> + Sync,
+{
+    if policy == NanPolicy::Error {
+        if has_nan(query) {
+            return Err(PyValueError::new_err("query contains NaN"));
+        }
+        if let Some(i) = store.iter().position(|row| has_nan(row.as_ref())) {
+            return Err(PyValueError::new_err(format!("store row {i} contains NaN")));
+        }
+    }
+
+    if query.is_empty() || store.is_empty() {
+        return Ok(vec![0.0; store.len()]);
+    }
+
+    // Pre-compute query norm once
+    let skip_nan = policy == NanPolicy::Skip;
+    let query_norm = l2_norm(query, skip_nan);
+    if query_norm == 0.0 {
+        return Ok(vec![0.0; store.len()]);
+    }
+
+    Ok(map_rows_with_threshold(store, threshold, |row| {
+        let row = row.as_ref();
+        if skip_nan {
+            cosine_skip_nan_with_prenorm(query, query_norm, row)
+        } else {
+            cosine_checked_with_prenorm(query, query_norm, row)
+        }
+        .map_or(0.0, |s| if clamp { s.clamp(-1.0, 1.0) } else { s })
+    }))
+}
+
+/// Lenient body of `cosine_similarity`: bad input scores 0.0.
+fn cosine_pair(a: &[f64], b: &[f64]) -> f64 {
     if a.is_empty() || b.is_empty() || a.len() != b.len() {
         return 0.0;
     }
@@ -27,50 +1310,200 @@ pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>) -> f64 {
     }
 }
 
-/// Compute cosine similarity of one query vector against N stored vectors.
-/// The loop runs in Rust with rayon parallelism for large batches.
-#[pyfunction]
-pub fn cosine_similarity_batch(query: Vec<f64>, store: Vec<Vec<f64>>) -> Vec<f64> {
-    if query.is_empty() || store.is_empty() {
-        return vec![0.0; store.len()];
+/// Reject input that lenient cosine would silently score 0.0.
+fn check_cosine_input(a: &[f64], b: &[f64]) -> PyResult<()> {
+    if a.is_empty() || b.is_empty() {
+        return Err(PyValueError::new_err(format!(
+            "empty vector (lengths {} and {})",
+            a.len(),
+            b.len()
+        )));
+    }
+    if a.len() != b.len() {
+        return Err(PyValueError::new_err(format!(
+            "length mismatch: {} vs {}",
+            a.len(),
+            b.len()
+        )));
     }
+    if l2_norm(a, false) == 0.0 {
+        return Err(PyValueError::new_err("vector a has zero norm"));
+    }
+    if l2_norm(b, false) == 0.0 {
+        return Err(PyValueError::new_err("vector b has zero norm"));
+    }
+    Ok(())
+}
 
-    // Pre-compute query norm once
-    let query_norm_sq: f64 = query.iter().map(|x| x * x).sum();
-    let query_norm = query_norm_sq.sqrt();
-    if query_norm == 0.0 {
-        return vec![0.0; store.len()];
+#[inline]
+fn cosine_sim_with_prenorm(query: &[f64], query_norm: f64, vec: &[f64]) -> f64 {
+    cosine_checked_with_prenorm(query, query_norm, vec).unwrap_or(0.0)
+}
+
+/// Cosine against a pre-normed query, or `None` when it is undefined
+/// (dimension mismatch, zero norm, or a non-finite result).
+#[inline]
+fn cosine_checked_with_prenorm<T: Component>(
+    query: &[T],
+    query_norm: f64,
+    vec: &[T],
+) -> Option<f64> {
+    if vec.len() != query.len() {
+        return None;
     }
+    let (dot, norm_b) = T::dot_and_norm_sq(query, vec);
+    finish_cosine(dot, query_norm, norm_b)
+}
 
-    let threshold = 256; // use rayon only for larger batches
-    if store.len() < threshold {
-        store
-            .iter()
-            .map(|vec| cosine_sim_with_prenorm(&query, query_norm, vec))
-            .collect()
-    } else {
-        store
-            .par_iter()
-            .map(|vec| cosine_sim_with_prenorm(&query, query_norm, vec))
-            .collect()
+/// Element type of the cosine kernels; accumulation is always in f64.
+trait Component: Copy + Into<f64> {
+    /// Dot product of two equal-length slices and the squared norm of `vec`.
+    #[inline]
+    fn dot_and_norm_sq(query: &[Self], vec: &[Self]) -> (f64, f64) {
+        let mut dot = 0.0_f64;
+        let mut norm_b = 0.0_f64;
+        for (&x, &y) in query.iter().zip(vec.iter()) {
+            let (x, y): (f64, f64) = (x.into(), y.into());
+            dot += x * y;
+            norm_b += y * y;
+        }
+        (dot, norm_b)
     }
 }
 
+impl Component for f32 {}
+
+#[cfg(not(feature = "simd"))]
+impl Component for f64 {}
+
+/// SIMD build of the f64 kernel behind every cosine path: the dot product and
+/// row norm are accumulated four lanes at a time, with a scalar loop for the tail.
+#[cfg(feature = "simd")]
+impl Component for f64 {
+    #[inline]
+    fn dot_and_norm_sq(query: &[f64], vec: &[f64]) -> (f64, f64) {
+        use wide::f64x4;
+
+        let mut dot = f64x4::ZERO;
+        let mut norm_b = f64x4::ZERO;
+        let q_chunks = query.chunks_exact(4);
+        let v_chunks = vec.chunks_exact(4);
+        let (q_tail, v_tail) = (q_chunks.remainder(), v_chunks.remainder());
+        for (q, v) in q_chunks.zip(v_chunks) {
+            let q = f64x4::from([q[0], q[1], q[2], q[3]]);
+            let v = f64x4::from([v[0], v[1], v[2], v[3]]);
+            dot = q.mul_add(v, dot);
+            norm_b = v.mul_add(v, norm_b);
+        }
+        let mut dot = dot.reduce_add();
+        let mut norm_b = norm_b.reduce_add();
+        for (&x, &y) in q_tail.iter().zip(v_tail) {
+            dot += x * y;
+            norm_b += y * y;
+        }
+        (dot, norm_b)
+    }
+}
+
+/// `cosine_checked_with_prenorm` with NaN components treated as 0.0.
 #[inline]
-fn cosine_sim_with_prenorm(query: &[f64], query_norm: f64, vec: &[f64]) -> f64 {
+fn cosine_skip_nan_with_prenorm<T: Copy + Into<f64>>(
+    query: &[T],
+    query_norm: f64,
+    vec: &[T],
+) -> Option<f64> {
     if vec.len() != query.len() {
+        return None;
+    }
+    let mut dot = 0.0_f64;
+    let mut norm_b = 0.0_f64;
+    for (&x, &y) in query.iter().zip(vec.iter()) {
+        let (x, y) = (nan_to_zero(x.into()), nan_to_zero(y.into()));
+        dot += x * y;
+        norm_b += y * y;
+    }
+    finish_cosine(dot, query_norm, norm_b)
+}
+
+#[inline]
+fn l1_distance(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return f64::INFINITY;
+    }
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum()
+}
+
+#[inline]
+fn squared_l2_distance(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return f64::INFINITY;
+    }
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| {
+            let d = x - y;
+            d * d
+        })
+        .sum()
+}
+
+fn mean_centered(v: &[f64]) -> Vec<f64> {
+    if v.is_empty() {
+        return Vec::new();
+    }
+    let mean = v.iter().sum::<f64>() / v.len() as f64;
+    v.iter().map(|x| x - mean).collect()
+}
+
+/// 1-based ranks of `v`, with each run of tied values given its average rank.
+fn average_ranks(v: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..v.len()).collect();
+    order.sort_by(|&a, &b| v[a].total_cmp(&v[b]));
+
+    let mut ranks = vec![0.0; v.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && v[order[end]] == v[order[start]] {
+            end += 1;
+        }
+        // Positions start..end hold ranks start+1..=end; their mean is shared.
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Pearson correlation of `vec` against an already mean-centered query.
+fn pearson_with_centered(centered_query: &[f64], query_norm: f64, vec: &[f64]) -> f64 {
+    if vec.is_empty() || vec.len() != centered_query.len() || query_norm == 0.0 {
         return 0.0;
     }
+    let mean = vec.iter().sum::<f64>() / vec.len() as f64;
     let mut dot = 0.0_f64;
     let mut norm_b = 0.0_f64;
-    for (x, y) in query.iter().zip(vec.iter()) {
+    for (x, y) in centered_query.iter().zip(vec.iter()) {
+        let y = y - mean;
         dot += x * y;
         norm_b += y * y;
     }
-    let denom = query_norm * norm_b.sqrt();
+    finish_cosine(dot, query_norm, norm_b).map_or(0.0, |r| r.clamp(-1.0, 1.0))
+}
+
+/// Cosine of two vectors whose norms are already known; 0.0 when undefined.
+#[inline]
+fn cosine_with_norms(a: &[f64], norm_a: f64, b: &[f64], norm_b: f64) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let denom = norm_a * norm_b;
     if denom == 0.0 {
         return 0.0;
     }
+    let dot: f64 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
     let result = dot / denom;
     if result.is_nan() || result.is_infinite() {
         0.0
@@ -78,3 +1511,159 @@ fn cosine_sim_with_prenorm(query: &[f64], query_norm: f64, vec: &[f64]) -> f64 {
         result
     }
 }
+
+#[inline]
+fn finish_cosine(dot: f64, query_norm: f64, norm_b_sq: f64) -> Option<f64> {
+    let denom = query_norm * norm_b_sq.sqrt();
+    if denom == 0.0 {
+        return None;
+    }
+    let result = dot / denom;
+    if result.is_nan() || result.is_infinite() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+#[inline]
+fn nan_to_zero(x: f64) -> f64 {
+    if x.is_nan() {
+        0.0
+    } else {
+        x
+    }
+}
+
+fn l2_norm<T: Copy + Into<f64>>(v: &[T], skip_nan: bool) -> f64 {
+    v.iter()
+        .map(|&x| {
+            let x = x.into();
+            if skip_nan {
+                nan_to_zero(x) * nan_to_zero(x)
+            } else {
+                x * x
+            }
+        })
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// ValueError naming `what` if `v` has a negative component.
+fn check_non_negative(what: &str, v: &[f64]) -> PyResult<()> {
+    match v.iter().position(|&x| x < 0.0) {
+        Some(j) => Err(PyValueError::new_err(format!(
+            "{what} has negative component {} at index {j}",
+            v[j]
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn is_sorted_sparse(v: &[(u32, f64)]) -> bool {
+    v.windows(2).all(|w| w[0].0 < w[1].0)
+}
+
+fn has_nan<T: Copy + Into<f64>>(v: &[T]) -> bool {
+    v.iter().any(|&x| x.into().is_nan())
+}
+
+/// Batches smaller than this run serially; rayon overhead isn't worth it below.
+pub(crate) const PARALLEL_THRESHOLD: usize = 256;
+
+/// Map `f` over every stored row, switching to rayon for larger batches.
+fn map_rows<R, T, F>(rows: &[R], f: F) -> Vec<T>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(&R) -> T + Sync + Send,
+{
+    map_rows_with_threshold(rows, PARALLEL_THRESHOLD, f)
+}
+
+/// `map_rows` with a caller-chosen row count at which rayon takes over.
+fn map_rows_with_threshold<R, T, F>(rows: &[R], threshold: usize, f: F) -> Vec<T>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(&R) -> T + Sync + Send,
+{
+    if rows.len() < threshold {
+        rows.iter().map(f).collect()
+    } else {
+        rows.par_iter().map(f).collect()
+    }
+}
+
+/// Score every stored row and keep the best `k` as (index, score), best first.
+/// Rows scoring below `min_score` (when given) are dropped before the heap.
+pub(crate) fn topk_rows<R, F>(
+    rows: &[R],
+    k: usize,
+    min_score: Option<f64>,
+    score: F,
+) -> Vec<(usize, f64)>
+where
+    R: Sync,
+    F: Fn(&R) -> f64 + Sync + Send,
+{
+    let push = |top: &mut TopK<usize>, i: usize, row: &R| {
+        let s = score(row);
+        if min_score.is_none_or(|min| s >= min) {
+            top.push(i, s);
+        }
+    };
+    let top = if rows.len() < PARALLEL_THRESHOLD {
+        let mut top = TopK::new(k);
+        for (i, row) in rows.iter().enumerate() {
+            push(&mut top, i, row);
+        }
+        top
+    } else {
+        rows.par_iter()
+            .enumerate()
+            .fold(
+                || TopK::new(k),
+                |mut top, (i, row)| {
+                    push(&mut top, i, row);
+                    top
+                },
+            )
+            .reduce(|| TopK::new(k), TopK::merge)
+    };
+    top.into_sorted_vec()
+}
+
+/// Map `f` over `0..n`, switching to rayon for larger ranges.
+/// Indexed ranges collect in order, so the parallel path keeps input order.
+pub(crate) fn map_range<T, F>(n: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync + Send,
+{
+    if n < PARALLEL_THRESHOLD {
+        (0..n).map(f).collect()
+    } else {
+        (0..n).into_par_iter().map(f).collect()
+    }
+}
+
+/// Keep the `Some` results of `f(index, row)`, in row order, going parallel for larger batches.
+fn filter_map_rows<R, T, F>(rows: &[R], f: F) -> Vec<T>
+where
+    R: Sync,
+    T: Send,
+    F: Fn(usize, &R) -> Option<T> + Sync + Send,
+{
+    if rows.len() < PARALLEL_THRESHOLD {
+        rows.iter()
+            .enumerate()
+            .filter_map(|(i, row)| f(i, row))
+            .collect()
+    } else {
+        rows.par_iter()
+            .enumerate()
+            .filter_map(|(i, row)| f(i, row))
+            .collect()
+    }
+}
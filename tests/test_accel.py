@@ -17,6 +17,15 @@ from engram.utils.math import (
 )
 from engram.core.retrieval import tokenize, bm25_score_batch
 
+try:
+    import engram_accel
+except ImportError:
+    engram_accel = None
+
+requires_accel = pytest.mark.skipif(
+    engram_accel is None, reason="engram_accel is not installed"
+)
+
 
 # ── cosine_similarity ───────────────────────────────────────────────────
 
@@ -192,3 +201,607 @@ class TestDecayAccel:
         """Ensure traces batch function is available."""
         from engram.core.traces import decay_traces_batch
         assert callable(decay_traces_batch)
+
+
+# ── Linear decay ───────────────────────────────────────────────────────
+
+@requires_accel
+class TestLinearDecay:
+    def test_decays_to_floor(self):
+        decay = engram_accel.calculate_decayed_strength_linear
+        assert decay(0.9, 2.0, 0.1, 0.2) == pytest.approx(0.7)
+        assert decay(0.9, 20.0, 0.1, 0.2) == pytest.approx(0.2)
+
+    def test_nan_floor_counts_as_zero(self):
+        decay = engram_accel.calculate_decayed_strength_linear
+        assert decay(0.9, 2.0, 0.1, float("nan")) == pytest.approx(0.7)
+        assert decay(0.9, 20.0, 0.1, float("nan")) == 0.0
+        assert decay(float("nan"), 1.0, 0.1, float("nan")) == 0.0
+
+
+# ── idf_variant ────────────────────────────────────────────────────────
+
+IDF_QUERY = ["a", "b"]
+IDF_DOCS = [["a", "b", "a"], ["a"], ["c", "a"]]
+
+
+@requires_accel
+class TestIdfVariant:
+    def test_default_matches_baseline(self):
+        scores = engram_accel.bm25_score_batch(IDF_QUERY, IDF_DOCS, 3, 2.0, 1.5, 0.75)
+        assert scores == pytest.approx(
+            [0.9650232706180901, 0.1722985711284162, 0.13353139262452257]
+        )
+
+    def test_default_matches_python_formula(self):
+        from engram.core.retrieval import calculate_bm25_score
+        doc_freq = {t: sum(1 for d in IDF_DOCS if t in d) for t in IDF_QUERY}
+        expected = [
+            calculate_bm25_score(set(IDF_QUERY), doc, doc_freq, 3, 2.0)
+            for doc in IDF_DOCS
+        ]
+        scores = engram_accel.bm25_score_batch(IDF_QUERY, IDF_DOCS, 3, 2.0, 1.5, 0.75)
+        assert scores == pytest.approx(expected)
+
+    @pytest.mark.parametrize("variant", ["bm25", "bm25_nonneg", "lucene"])
+    def test_variants_agree_on_consistent_corpus(self, variant):
+        scores = engram_accel.bm25_score_batch(
+            IDF_QUERY, IDF_DOCS, 3, 2.0, 1.5, 0.75, variant
+        )
+        assert scores == pytest.approx(
+            [0.9650232706180901, 0.1722985711284162, 0.13353139262452257]
+        )
+
+    def test_undercounted_total_docs(self):
+        # total_docs below the document frequency drives the smoothed IDF negative.
+        negative = [-0.4539153847196946, -0.72208488765861, -0.5596157879354228]
+        for variant in ("bm25", "lucene"):
+            scores = engram_accel.bm25_score_batch(
+                IDF_QUERY, IDF_DOCS, 1, 2.0, 1.5, 0.75, variant
+            )
+            assert scores == pytest.approx(negative)
+        clamped = engram_accel.bm25_score_batch(
+            IDF_QUERY, IDF_DOCS, 1, 2.0, 1.5, 0.75, "bm25_nonneg"
+        )
+        assert clamped == pytest.approx([0.23484250812390273, 0.0, 0.0])
+
+    def test_unknown_variant(self):
+        with pytest.raises(ValueError):
+            engram_accel.bm25_score_batch(IDF_QUERY, IDF_DOCS, 3, 2.0, 1.5, 0.75, "okapi")
+
+
+# ── DecayTable ─────────────────────────────────────────────────────────
+
+@requires_accel
+class TestDecayTable:
+    def exact(self, days, access):
+        return engram_accel.calculate_decayed_strength(1.0, days, 0.1, access, 0.5)
+
+    def test_grid_points_match_exact_decay(self):
+        table = engram_accel.DecayTable(0.1, 0.5, 30.0, 0.5, 16)
+        days = [0.0, 0.5, 7.0, 29.5, 30.0]
+        access = [0, 1, 3, 16, 8]
+        expected = [self.exact(d, a) for d, a in zip(days, access)]
+        assert table.lookup_batch(days, access) == pytest.approx(expected)
+
+    def test_off_grid_within_step(self):
+        table = engram_accel.DecayTable(0.1, 0.5, 30.0, 0.01, 16)
+        days = [0.123, 3.456, 12.345]
+        result = table.lookup_batch(days, [2, 2, 2])
+        expected = [self.exact(d, 2) for d in days]
+        assert result == pytest.approx(expected, rel=1e-3)
+
+    def test_beyond_max_days_is_exact(self):
+        table = engram_accel.DecayTable(0.1, 0.5, 10.0)
+        assert table.lookup_batch([42.42], [5]) == pytest.approx([self.exact(42.42, 5)])
+
+    def test_access_count_clamped(self):
+        table = engram_accel.DecayTable(0.1, 0.5, 10.0, 0.5, 4)
+        assert table.lookup_batch([5.0], [100]) == pytest.approx([self.exact(5.0, 4)])
+
+    @pytest.mark.parametrize(
+        "args",
+        [
+            (0.1, 0.5, float("inf")),
+            (0.1, 0.5, 1e18),
+            (0.1, 0.5, -1.0),
+            (0.1, 0.5, 10.0, 0.0),
+            (0.1, 0.5, 10.0, float("inf")),
+            (0.1, 0.5, 1e6, 0.1, 2**32 - 1),
+        ],
+    )
+    def test_rejects_bad_sizes(self, args):
+        with pytest.raises(ValueError):
+            engram_accel.DecayTable(*args)
+
+    def test_length_mismatch(self):
+        table = engram_accel.DecayTable(0.1, 0.5, 10.0)
+        with pytest.raises(ValueError):
+            table.lookup_batch([1.0, 2.0], [1])
+
+
+# ── VectorStore ────────────────────────────────────────────────────────
+
+@requires_accel
+class TestVectorStore:
+    def test_search_ranks_by_cosine(self):
+        store = engram_accel.VectorStore()
+        store.add(1, [1.0, 0.0])
+        store.add(2, [0.0, 1.0])
+        store.add(3, [1.0, 1.0])
+        hits = store.search([1.0, 0.0], 2)
+        assert [i for i, _ in hits] == [1, 3]
+        assert hits[0][1] == pytest.approx(1.0)
+        assert hits[1][1] == pytest.approx(1 / math.sqrt(2))
+
+    def test_add_replaces_existing_id(self):
+        store = engram_accel.VectorStore()
+        store.add(7, [1.0, 0.0])
+        store.add(7, [0.0, 1.0])
+        assert len(store) == 1
+        assert store.search([0.0, 1.0], 1) == [(7, pytest.approx(1.0))]
+
+    def test_remove_keeps_other_ids(self):
+        store = engram_accel.VectorStore()
+        for i in range(4):
+            store.add(i, [1.0, float(i)])
+        assert store.remove(0)
+        assert not store.remove(0)
+        assert len(store) == 3
+        assert 0 not in store and 3 in store
+        # The last row was swapped into the freed slot; it must still be found.
+        assert store.search([1.0, 3.0], 1)[0][0] == 3
+
+    def test_dimension_mismatch(self):
+        store = engram_accel.VectorStore()
+        store.add(1, [1.0, 0.0])
+        with pytest.raises(ValueError):
+            store.add(2, [1.0, 0.0, 0.0])
+        with pytest.raises(ValueError):
+            store.search([1.0], 1)
+
+
+# ── VectorStore snapshots ──────────────────────────────────────────────
+
+@requires_accel
+class TestVectorStoreSnapshot:
+    def saved_store(self, tmp_path):
+        store = engram_accel.VectorStore()
+        store.add(10, [1.0, 2.0, 3.0])
+        store.add(20, [-1.0, 0.5, 0.0])
+        store.add(30, [0.0, 0.0, 1.0])
+        path = tmp_path / "store.bin"
+        store.save(str(path))
+        return store, path
+
+    def test_round_trip(self, tmp_path):
+        store, path = self.saved_store(tmp_path)
+        loaded = engram_accel.VectorStore.load(str(path))
+        assert len(loaded) == 3
+        assert all(i in loaded for i in (10, 20, 30))
+        query = [0.5, 1.0, 2.0]
+        assert loaded.search(query, 3) == store.search(query, 3)
+
+    def test_loaded_store_accepts_updates(self, tmp_path):
+        _, path = self.saved_store(tmp_path)
+        loaded = engram_accel.VectorStore.load(str(path))
+        assert loaded.remove(10)
+        loaded.add(40, [1.0, 2.0, 3.0])
+        assert loaded.search([1.0, 2.0, 3.0], 1)[0][0] == 40
+
+    def test_missing_file(self, tmp_path):
+        with pytest.raises(IOError):
+            engram_accel.VectorStore.load(str(tmp_path / "missing.bin"))
+
+    def test_truncated_file(self, tmp_path):
+        _, path = self.saved_store(tmp_path)
+        path.write_bytes(path.read_bytes()[:20])
+        with pytest.raises(ValueError):
+            engram_accel.VectorStore.load(str(path))
+
+    def test_bad_magic(self, tmp_path):
+        _, path = self.saved_store(tmp_path)
+        data = path.read_bytes()
+        path.write_bytes(b"NOTASTOR" + data[8:])
+        with pytest.raises(ValueError, match="not a vector store"):
+            engram_accel.VectorStore.load(str(path))
+
+    def test_version_mismatch(self, tmp_path):
+        _, path = self.saved_store(tmp_path)
+        data = path.read_bytes()
+        # The little-endian u32 version follows the 8-byte magic.
+        path.write_bytes(data[:8] + (99).to_bytes(4, "little") + data[12:])
+        with pytest.raises(ValueError, match="version 99"):
+            engram_accel.VectorStore.load(str(path))
+
+
+# ── Bm25Index ──────────────────────────────────────────────────────────
+
+@requires_accel
+class TestBm25Index:
+    def expected(self, docs, query, k=10):
+        """Rank the live documents with the stateless batch scorer."""
+        ids = sorted(docs)
+        corpus = [docs[i] for i in ids]
+        avg_len = sum(len(d) for d in corpus) / len(corpus)
+        scores = engram_accel.bm25_score_batch(query, corpus, len(corpus), avg_len, 1.5, 0.75)
+        hits = sorted(
+            ((i, s) for i, s in zip(ids, scores) if s != 0.0),
+            key=lambda hit: (-hit[1], hit[0]),
+        )
+        return hits[:k]
+
+    def assert_matches(self, index, docs, query):
+        hits = index.search(query, 10)
+        expected = self.expected(docs, query)
+        assert [i for i, _ in hits] == [i for i, _ in expected]
+        assert [s for _, s in hits] == pytest.approx([s for _, s in expected])
+
+    def test_matches_batch_scoring(self):
+        docs = {
+            "a": ["rust", "python", "rust"],
+            "b": ["python", "memory"],
+            "c": ["memory", "decay", "memory", "trace"],
+        }
+        index = engram_accel.Bm25Index()
+        for doc_id, tokens in docs.items():
+            index.add_document(doc_id, tokens)
+        assert len(index) == 3
+        self.assert_matches(index, docs, ["rust", "memory"])
+
+    def test_replace_updates_statistics(self):
+        docs = {"a": ["rust", "rust"], "b": ["python"], "c": ["memory"]}
+        index = engram_accel.Bm25Index()
+        for doc_id, tokens in docs.items():
+            index.add_document(doc_id, tokens)
+        docs["a"] = ["python", "memory", "memory", "decay"]
+        index.add_document("a", docs["a"])
+        assert len(index) == 3
+        assert index.search(["rust"], 10) == []
+        self.assert_matches(index, docs, ["python", "memory"])
+
+    def test_remove_updates_statistics(self):
+        docs = {"a": ["rust"], "b": ["rust", "python"], "c": ["python", "python"]}
+        index = engram_accel.Bm25Index()
+        for doc_id, tokens in docs.items():
+            index.add_document(doc_id, tokens)
+        assert index.remove_document("b")
+        assert not index.remove_document("b")
+        del docs["b"]
+        assert len(index) == 2
+        self.assert_matches(index, docs, ["rust", "python"])
+
+    def test_empty_index(self):
+        index = engram_accel.Bm25Index()
+        assert index.search(["rust"], 5) == []
+        index.add_document("a", ["rust"])
+        index.remove_document("a")
+        assert len(index) == 0
+        assert index.search(["rust"], 5) == []
+
+
+# ── HnswIndex ──────────────────────────────────────────────────────────
+
+@requires_accel
+class TestHnswIndex:
+    def test_finds_nearest(self):
+        index = engram_accel.HnswIndex()
+        for i in range(50):
+            angle = i * math.pi / 100
+            index.add(i, [math.cos(angle), math.sin(angle)])
+        index.build()
+        hits = index.search([1.0, 0.0], 3)
+        assert [i for i, _ in hits] == [0, 1, 2]
+        assert hits[0][1] == pytest.approx(1.0, abs=1e-6)
+
+    def test_add_replaces_duplicate_id(self):
+        index = engram_accel.HnswIndex()
+        index.add(1, [1.0, 0.0])
+        index.add(2, [0.0, 1.0])
+        index.add(1, [0.0, -1.0])
+        assert len(index) == 2
+        index.build()
+        hits = index.search([1.0, 0.0], 2)
+        assert sorted(i for i, _ in hits) == [1, 2]
+        assert dict(hits)[1] == pytest.approx(0.0, abs=1e-6)
+
+    def test_zero_vectors_score_zero(self):
+        index = engram_accel.HnswIndex()
+        index.add(1, [0.0, 0.0])
+        index.add(2, [1.0, 1.0])
+        index.build()
+        hits = index.search([1.0, 1.0], 2)
+        assert hits[0] == (2, pytest.approx(1.0, abs=1e-6))
+        assert hits[1] == (1, 0.0)
+        assert all(score == 0.0 for _, score in index.search([0.0, 0.0], 2))
+
+    def test_search_before_build(self):
+        index = engram_accel.HnswIndex()
+        index.add(1, [1.0, 0.0])
+        with pytest.raises(ValueError):
+            index.search([1.0, 0.0], 1)
+
+    @pytest.mark.parametrize("m", [0, 1, 257, 300])
+    def test_build_rejects_bad_m(self, m):
+        index = engram_accel.HnswIndex()
+        index.add(1, [1.0, 0.0])
+        with pytest.raises(ValueError):
+            index.build(m=m)
+
+    def test_build_rejects_zero_ef_construction(self):
+        index = engram_accel.HnswIndex()
+        index.add(1, [1.0, 0.0])
+        with pytest.raises(ValueError):
+            index.build(ef_construction=0)
+
+
+# ── ProductQuantizer ───────────────────────────────────────────────────
+
+PQ_VECTORS = [
+    [0.0, 0.0, 1.0, 1.0],
+    [1.0, 0.0, 0.0, 2.0],
+    [0.0, 3.0, 2.0, 0.0],
+    [2.0, 2.0, 1.0, 3.0],
+]
+
+
+@requires_accel
+class TestProductQuantizer:
+    def trained(self):
+        # One centroid per training vector makes every codebook exact.
+        pq = engram_accel.ProductQuantizer()
+        pq.train(PQ_VECTORS, 2, len(PQ_VECTORS))
+        return pq
+
+    def test_encode_shape(self):
+        codes = self.trained().encode(PQ_VECTORS)
+        assert len(codes) == len(PQ_VECTORS)
+        assert all(len(code) == 2 for code in codes)
+        assert all(0 <= c < len(PQ_VECTORS) for code in codes for c in code)
+
+    def test_adc_matches_exact_distance(self):
+        pq = self.trained()
+        codes = pq.encode(PQ_VECTORS)
+        query = [0.5, 1.0, 2.0, 0.0]
+        expected = [sum((q - x) ** 2 for q, x in zip(query, v)) for v in PQ_VECTORS]
+        assert pq.asymmetric_distance(query, codes) == pytest.approx(expected, rel=1e-6)
+
+    def test_adc_is_zero_for_encoded_vector(self):
+        pq = self.trained()
+        codes = pq.encode(PQ_VECTORS)
+        for i, v in enumerate(PQ_VECTORS):
+            assert pq.asymmetric_distance(v, codes)[i] == pytest.approx(0.0, abs=1e-6)
+
+    def test_untrained(self):
+        pq = engram_accel.ProductQuantizer()
+        with pytest.raises(ValueError):
+            pq.encode(PQ_VECTORS)
+
+    def test_bad_training_arguments(self):
+        pq = engram_accel.ProductQuantizer()
+        with pytest.raises(ValueError):
+            pq.train(PQ_VECTORS, 3, 2)
+        with pytest.raises(ValueError):
+            pq.train(PQ_VECTORS, 2, 5)
+
+    def test_malformed_code(self):
+        pq = self.trained()
+        with pytest.raises(ValueError):
+            pq.asymmetric_distance(PQ_VECTORS[0], [[0]])
+        with pytest.raises(ValueError):
+            pq.asymmetric_distance(PQ_VECTORS[0], [[0, 9]])
+
+
+# ── int8 cosine ────────────────────────────────────────────────────────
+
+@requires_accel
+class TestCosineInt8:
+    def test_long_vectors_do_not_overflow(self):
+        # 127² per component overflows a 32-bit sum past ~133k dimensions.
+        n = 200_000
+        scores = engram_accel.cosine_similarity_batch_i8(
+            [127] * n, 1.0, [[127] * n, [-127] * n], [1.0, 1.0]
+        )
+        assert scores == pytest.approx([1.0, -1.0])
+
+
+# ── Jaccard ────────────────────────────────────────────────────────────
+
+@requires_accel
+class TestJaccard:
+    def test_similarity(self):
+        scores = engram_accel.jaccard_similarity_batch([1, 2, 3], [[2, 3, 4], [], [1, 2, 3]])
+        assert scores == pytest.approx([0.5, 0.0, 1.0])
+        assert engram_accel.jaccard_similarity_batch([], [[]]) == [1.0]
+
+    @pytest.mark.parametrize(
+        "query, store",
+        [([2, 1], [[1]]), ([1, 1], [[1]]), ([1], [[1], [3, 2]]), ([1], [[4, 4]])],
+    )
+    def test_rejects_unsorted_or_duplicate_ids(self, query, store):
+        with pytest.raises(ValueError):
+            engram_accel.jaccard_similarity_batch(query, store)
+
+
+# ── Token positions ────────────────────────────────────────────────────
+
+NON_ASCII_TEXTS = [
+    "ΟΔΟΣ İstanbul café",
+    "Straße ΣΊΣΥΦΟΣ, naïve İİ x",
+    "ẞig ǅemal — ΑΣ ΣΑ",
+]
+
+
+@requires_accel
+class TestTokenPositions:
+    @pytest.mark.parametrize("text", NON_ASCII_TEXTS)
+    def test_tokens_match_tokenize(self, text):
+        tokens = [t for t, _ in engram_accel.tokenize_with_positions(text)]
+        assert tokens == engram_accel.tokenize(text)
+
+    @pytest.mark.parametrize("text", NON_ASCII_TEXTS)
+    def test_offsets_map_back_to_tokens(self, text):
+        # Offsets are byte offsets into the UTF-8 text.
+        data = text.encode()
+        positions = engram_accel.tokenize_with_positions(text)
+        tokens = [t for t, _ in positions]
+        for i, (token, start) in enumerate(positions):
+            assert engram_accel.tokenize(data[start:].decode())[0] == token
+            assert engram_accel.tokenize(data[:start].decode()) == tokens[:i]
+
+    @pytest.mark.parametrize("text", NON_ASCII_TEXTS)
+    def test_snippet_range_covers_token(self, text):
+        data = text.encode()
+        for token in engram_accel.tokenize(text):
+            snippet, start, end = engram_accel.best_snippet(text, [token], 1)
+            assert data[start:end].decode() == snippet
+            assert token in engram_accel.tokenize(snippet)
+
+    def test_expanding_lowercase_offsets(self):
+        # "İ" lowercases to "i" plus a combining dot, which splits the token.
+        assert engram_accel.tokenize_with_positions("İstanbul") == [
+            ("i", 0),
+            ("stanbul", 2),
+        ]
+        # A final sigma lowercases by context but keeps its byte offset.
+        assert engram_accel.tokenize_with_positions("ΟΔΟΣ ΑΣ") == [
+            ("οδος", 0),
+            ("ας", 9),
+        ]
+
+
+# ── bm25_topk ──────────────────────────────────────────────────────────
+
+TOPK_QUERY = ["rust", "memory"]
+TOPK_DOCS = [
+    ["python", "code"],
+    ["rust", "memory", "rust"],
+    ["memory", "decay"],
+    ["rust", "memory", "rust"],
+    ["graph"],
+    ["memory", "trace"],
+]
+
+
+@requires_accel
+class TestBm25TopK:
+    def expected(self, k, docs=TOPK_DOCS):
+        scores = engram_accel.bm25_score_batch(TOPK_QUERY, docs, len(docs), 2.0, 1.5, 0.75)
+        ranked = sorted(enumerate(scores), key=lambda hit: (-hit[1], hit[0]))
+        return ranked[:k]
+
+    @pytest.mark.parametrize("k", [0, 1, 2, 4])
+    def test_matches_sorted_batch_scores(self, k):
+        hits = engram_accel.bm25_topk(TOPK_QUERY, TOPK_DOCS, len(TOPK_DOCS), 2.0, 1.5, 0.75, k)
+        expected = self.expected(k)
+        assert [i for i, _ in hits] == [i for i, _ in expected]
+        assert [s for _, s in hits] == pytest.approx([s for _, s in expected])
+
+    def test_ties_go_to_smaller_index(self):
+        hits = engram_accel.bm25_topk(TOPK_QUERY, TOPK_DOCS, len(TOPK_DOCS), 2.0, 1.5, 0.75, 2)
+        assert [i for i, _ in hits] == [1, 3]
+        assert hits[0][1] == pytest.approx(hits[1][1])
+
+    def test_zero_scores_only_fill_short_results(self):
+        k = len(TOPK_DOCS)
+        hits = engram_accel.bm25_topk(TOPK_QUERY, TOPK_DOCS, k, 2.0, 1.5, 0.75, k)
+        assert [i for i, _ in hits] == [i for i, _ in self.expected(k)]
+        # Matching documents come first; non-matching ones trail at 0.0 by index.
+        assert [i for i, s in hits if s == 0.0] == [0, 4]
+        top4 = engram_accel.bm25_topk(TOPK_QUERY, TOPK_DOCS, k, 2.0, 1.5, 0.75, 4)
+        assert all(s > 0.0 for _, s in top4)
+
+    def test_large_batch_matches_serial_path(self):
+        # Enough documents to take the parallel path.
+        docs = [TOPK_DOCS[i % len(TOPK_DOCS)] for i in range(600)]
+        hits = engram_accel.bm25_topk(TOPK_QUERY, docs, len(docs), 2.0, 1.5, 0.75, 10)
+        expected = self.expected(10, docs)
+        assert [i for i, _ in hits] == [i for i, _ in expected]
+        assert [s for _, s in hits] == pytest.approx([s for _, s in expected])
+
+
+# ── mmr_rerank ─────────────────────────────────────────────────────────
+
+MMR_QUERY = [1.0, 0.0]
+# Candidate 1 nearly duplicates candidate 0; candidate 2 is less relevant but distinct.
+MMR_CANDIDATES = [[1.0, 0.0], [0.99, 0.14], [0.6, 0.8]]
+
+
+@requires_accel
+class TestMmrRerank:
+    def test_diversity_beats_near_duplicate(self):
+        assert engram_accel.mmr_rerank(MMR_QUERY, MMR_CANDIDATES, [], 0.3, 3) == [0, 2, 1]
+
+    def test_lambda_one_is_relevance_order(self):
+        assert engram_accel.mmr_rerank(MMR_QUERY, MMR_CANDIDATES, [], 1.0, 3) == [0, 1, 2]
+        assert engram_accel.mmr_rerank(
+            MMR_QUERY, MMR_CANDIDATES, [0.1, 0.2, 0.9], 1.0, 2
+        ) == [2, 1]
+
+    def test_k_larger_than_candidates(self):
+        assert len(engram_accel.mmr_rerank(MMR_QUERY, MMR_CANDIDATES, [], 0.5, 10)) == 3
+
+    @pytest.mark.parametrize("relevance", [[1.0], [1.0, 0.5, 0.2, 0.1]])
+    def test_relevance_length_mismatch(self, relevance):
+        with pytest.raises(ValueError, match="relevance has length"):
+            engram_accel.mmr_rerank(MMR_QUERY, MMR_CANDIDATES, relevance, 0.5, 2)
+
+
+# ── softmax ────────────────────────────────────────────────────────────
+
+@requires_accel
+class TestSoftmax:
+    def test_sums_to_one(self):
+        weights = engram_accel.softmax([1.0, 2.0, 3.0, -4.0], 0.5)
+        assert sum(weights) == pytest.approx(1.0)
+        assert weights[2] > weights[1] > weights[0] > weights[3]
+
+    def test_large_scores_are_stable(self):
+        weights = engram_accel.softmax([1000.0, 1000.0], 1.0)
+        assert weights == pytest.approx([0.5, 0.5])
+
+    @pytest.mark.parametrize("temperature", [0.0, -1.0, float("nan")])
+    def test_rejects_bad_temperature(self, temperature):
+        with pytest.raises(ValueError, match="temperature"):
+            engram_accel.softmax([1.0, 2.0], temperature)
+
+    @pytest.mark.parametrize(
+        "scores",
+        [
+            [float("inf"), 1.0],
+            [float("-inf"), float("-inf")],
+            [1.0, float("nan")],
+        ],
+    )
+    def test_rejects_non_finite_scores(self, scores):
+        with pytest.raises(ValueError, match="finite"):
+            engram_accel.softmax(scores, 1.0)
+
+
+# ── merge_topk ─────────────────────────────────────────────────────────
+
+@requires_accel
+class TestMergeTopK:
+    def test_duplicate_ids_keep_best_score(self):
+        shards = [[(1, 0.5), (2, 0.4)], [(3, 0.9), (1, 0.8)], [(2, 0.1)]]
+        assert engram_accel.merge_topk(shards, 10) == [(3, 0.9), (1, 0.8), (2, 0.4)]
+
+    def test_truncates_to_k_with_ties_by_id(self):
+        shards = [[(5, 0.7), (4, 0.2)], [(2, 0.7)]]
+        assert engram_accel.merge_topk(shards, 2) == [(2, 0.7), (5, 0.7)]
+
+    def test_unsorted_shards(self):
+        shards = [[(1, 0.1), (2, 0.9)], [(3, 0.5)]]
+        assert engram_accel.merge_topk(shards, 2) == [(2, 0.9), (3, 0.5)]
+
+    def test_nan_ranks_last(self):
+        nan = float("nan")
+        merged = engram_accel.merge_topk([[(1, nan), (2, -5.0)], [(3, 0.1)]], 10)
+        assert [i for i, _ in merged] == [3, 2, 1]
+        assert math.isnan(merged[-1][1])
+        assert engram_accel.merge_topk([[(1, nan), (2, 0.0)]], 1) == [(2, 0.0)]
+
+    def test_real_score_beats_nan_for_same_id(self):
+        nan = float("nan")
+        assert engram_accel.merge_topk([[(1, nan)], [(1, 0.3)]], 5) == [(1, 0.3)]
+        assert engram_accel.merge_topk([[(1, 0.3)], [(1, nan)]], 5) == [(1, 0.3)]